@@ -5,6 +5,9 @@ pub enum LayerShikaError {
     #[error("Failed to connect to Wayland: {0}")]
     WaylandConnection(#[from] wayland_client::ConnectError),
 
+    #[error("Failed to open Wayland socket: {0}")]
+    WaylandSocket(String),
+
     #[error("Failed to initialize Wayland globals: {0}")]
     GlobalInitialization(String),
 
@@ -35,6 +38,9 @@ pub enum LayerShikaError {
     #[error("Wayland protocol error: {0}")]
     WaylandProtocol(String),
 
+    #[error("Not supported by the compositor: {0}")]
+    UnsupportedProtocol(String),
+
     #[error("Failed to set platform: {0}")]
     PlatformSetup(String),
 
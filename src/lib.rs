@@ -4,4 +4,17 @@ mod rendering;
 mod windowing;
 
 pub use reexports::*;
+pub use rendering::egl_context::SharedGlContext;
 pub use windowing::builder::WindowingSystemBuilder as LayerShika;
+#[cfg(feature = "unstable-slint-internals")]
+pub use windowing::color_scheme::ColorScheme;
+#[cfg(feature = "headless")]
+pub use windowing::headless::HeadlessWindowingSystem;
+pub use windowing::input::{FilterAction, InputEvent, InputSource};
+pub use windowing::keyboard::Modifiers;
+#[cfg(feature = "headless")]
+pub use windowing::layer_surface::LayerSurfaceRequest;
+pub use windowing::layouts::LayoutSet;
+pub use windowing::outputs::OutputInfo;
+pub use windowing::snapshot::ConfigSnapshot;
+pub use windowing::OptionalProtocols;
@@ -1,5 +1,10 @@
 pub use slint;
 pub use slint_interpreter;
+pub use xkbcommon::xkb::Keysym;
+
+pub mod glutin {
+    pub use glutin::*;
+}
 
 pub mod sctk {
     pub use smithay_client_toolkit::reexports::*;
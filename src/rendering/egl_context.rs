@@ -7,6 +7,7 @@ use glutin::{
     prelude::*,
     surface::{SurfaceAttributesBuilder, WindowSurface},
 };
+use log::info;
 use raw_window_handle::{
     RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
 };
@@ -15,21 +16,106 @@ use std::{
     ffi::{self, c_void, CStr},
     num::NonZeroU32,
     ptr::NonNull,
+    rc::Rc,
 };
 use wayland_client::backend::ObjectId;
 
+const GL_VERSION: u32 = 0x1F02;
+const GL_RENDERER: u32 = 0x1F01;
+const GL_EXTENSIONS: u32 = 0x1F03;
+
+/// `FemtoVG` creates a vertex array object for every frame, which is core from OpenGL 3.0 and
+/// OpenGL ES 3.0 on and an extension on OpenGL ES 2.0, the oldest version its shaders target.
+const MIN_GL_VERSION: (u32, u32) = (3, 0);
+const MIN_GLES_VERSION: (u32, u32) = (2, 0);
+const GLES_VERTEX_ARRAY_VERSION: (u32, u32) = (3, 0);
+const GLES_VERTEX_ARRAY_EXTENSION: &str = "GL_OES_vertex_array_object";
+
+type GlGetString = unsafe extern "C" fn(u32) -> *const u8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GlVersion {
+    embedded: bool,
+    major: u32,
+    minor: u32,
+}
+
+impl GlVersion {
+    /// Parses a `GL_VERSION` string, `"<major>.<minor>[.<release>] [vendor info]"` for desktop
+    /// OpenGL and `"OpenGL ES[-profile] <major>.<minor> [vendor info]"` for OpenGL ES.
+    fn parse(version: &str) -> Option<Self> {
+        let (embedded, rest) = version
+            .strip_prefix("OpenGL ES")
+            .map_or((false, version), |rest| (true, rest));
+        let number = rest
+            .split_whitespace()
+            .find(|token| token.starts_with(|c: char| c.is_ascii_digit()))?;
+        let mut parts = number.split('.').map(|part| {
+            part.chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>()
+                .parse::<u32>()
+        });
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().and_then(Result::ok).unwrap_or(0);
+        Some(Self {
+            embedded,
+            major,
+            minor,
+        })
+    }
+
+    const fn at_least(self, (major, minor): (u32, u32)) -> bool {
+        self.major > major || (self.major == major && self.minor >= minor)
+    }
+
+    const fn api(self) -> &'static str {
+        if self.embedded {
+            "OpenGL ES"
+        } else {
+            "OpenGL"
+        }
+    }
+
+    /// The requirement `FemtoVG` needs met that this version falls short of, if any.
+    fn missing_requirement(self, extensions: &str) -> Option<String> {
+        let (major, minor) = if self.embedded {
+            MIN_GLES_VERSION
+        } else {
+            MIN_GL_VERSION
+        };
+        if !self.at_least((major, minor)) {
+            return Some(format!("{} {major}.{minor}", self.api()));
+        }
+        let lacks_vertex_arrays = self.embedded
+            && !self.at_least(GLES_VERTEX_ARRAY_VERSION)
+            && !extensions
+                .split_whitespace()
+                .any(|extension| extension == GLES_VERTEX_ARRAY_EXTENSION);
+        lacks_vertex_arrays.then(|| GLES_VERTEX_ARRAY_EXTENSION.to_owned())
+    }
+}
+
 pub struct EGLContext {
-    context: PossiblyCurrentContext,
+    context: Rc<PossiblyCurrentContext>,
     surface: Surface<WindowSurface>,
 }
 
-#[derive(Default)]
+/// Handle to the OpenGL context of a surface.
+///
+/// Passed to [`crate::LayerShika::with_shared_gl_context`] to put another surface's context in
+/// the same share group, so textures and buffers created in one are usable in the other.
+#[derive(Clone)]
+pub struct SharedGlContext(Rc<PossiblyCurrentContext>);
+
+#[derive(Default, Clone)]
 pub struct EGLContextBuilder {
     display_id: Option<ObjectId>,
     surface_id: Option<ObjectId>,
     size: Option<PhysicalSize>,
     config_template: Option<ConfigTemplateBuilder>,
     context_attributes: Option<ContextAttributesBuilder>,
+    share_context: Option<SharedGlContext>,
 }
 
 impl EGLContextBuilder {
@@ -52,7 +138,6 @@ impl EGLContextBuilder {
         self
     }
 
-    #[allow(dead_code)]
     pub const fn with_config_template(mut self, config_template: ConfigTemplateBuilder) -> Self {
         self.config_template = Some(config_template);
         self
@@ -67,6 +152,11 @@ impl EGLContextBuilder {
         self
     }
 
+    pub fn with_share_context(mut self, share_context: SharedGlContext) -> Self {
+        self.share_context = Some(share_context);
+        self
+    }
+
     pub fn build(self) -> Result<EGLContext, LayerShikaError> {
         let display_id = self
             .display_id
@@ -78,16 +168,25 @@ impl EGLContextBuilder {
             .size
             .ok_or_else(|| LayerShikaError::InvalidInput("Size is required".into()))?;
 
-        let display_handle = create_wayland_display_handle(&display_id)?;
-        let glutin_display = unsafe { Display::new(display_handle) }.map_err(|e| {
-            LayerShikaError::EGLContextCreation(format!("Failed to create display: {e}"))
-        })?;
+        // Contexts can only share objects within one EGL display.
+        let glutin_display = if let Some(SharedGlContext(share_context)) = &self.share_context {
+            share_context.display()
+        } else {
+            let display_handle = create_wayland_display_handle(&display_id)?;
+            unsafe { Display::new(display_handle) }.map_err(|e| {
+                LayerShikaError::EGLContextCreation(format!("Failed to create display: {e}"))
+            })?
+        };
 
         let config_template = self.config_template.unwrap_or_default();
 
         let config = select_config(&glutin_display, config_template)?;
 
-        let context_attributes = self.context_attributes.unwrap_or_default();
+        let mut context_attributes = self.context_attributes.unwrap_or_default();
+        if let Some(SharedGlContext(share_context)) = &self.share_context {
+            info!("Sharing OpenGL objects with an existing context");
+            context_attributes = context_attributes.with_sharing(share_context.as_ref());
+        }
 
         let context = create_context(&glutin_display, &config, context_attributes)?;
 
@@ -98,7 +197,10 @@ impl EGLContextBuilder {
             .make_current(&surface)
             .map_err(|e| LayerShikaError::EGLContextCreation(format!("Unable to activate EGL context: {e}. This may indicate a problem with the graphics drivers.")))?;
 
-        Ok(EGLContext { context, surface })
+        Ok(EGLContext {
+            context: Rc::new(context),
+            surface,
+        })
     }
 }
 
@@ -107,6 +209,54 @@ impl EGLContext {
         EGLContextBuilder::new()
     }
 
+    pub fn shared(&self) -> SharedGlContext {
+        SharedGlContext(Rc::clone(&self.context))
+    }
+
+    /// Verifies that the context's OpenGL version, and its extensions where the version alone
+    /// isn't enough, meet what the `FemtoVG` renderer needs. Resolving entry points doesn't tell,
+    /// as drivers hand out stubs for functions they don't implement.
+    pub fn check_renderer_requirements(&self) -> Result<(), LayerShikaError> {
+        self.ensure_current()?;
+        let version = self
+            .gl_string(GL_VERSION)
+            .unwrap_or_else(|| "unknown".into());
+        let renderer = self
+            .gl_string(GL_RENDERER)
+            .unwrap_or_else(|| "unknown".into());
+        info!("OpenGL version: {version}, renderer: {renderer}");
+
+        let Some(parsed) = GlVersion::parse(&version) else {
+            return Err(LayerShikaError::FemtoVGRendererCreation(format!(
+                "The OpenGL driver ({renderer}) reports an unrecognized version `{version}`."
+            )));
+        };
+        let extensions = self.gl_string(GL_EXTENSIONS).unwrap_or_default();
+        if let Some(missing) = parsed.missing_requirement(&extensions) {
+            return Err(LayerShikaError::FemtoVGRendererCreation(format!(
+                "The OpenGL driver ({renderer}, {version}) lacks {missing}, which the FemtoVG renderer requires. Please update your graphics drivers."
+            )));
+        }
+        Ok(())
+    }
+
+    fn gl_string(&self, name: u32) -> Option<String> {
+        let get_string = self.get_proc_address(c"glGetString");
+        if get_string.is_null() {
+            return None;
+        }
+        let get_string: GlGetString = unsafe { std::mem::transmute(get_string) };
+        let value = unsafe { get_string(name) };
+        if value.is_null() {
+            return None;
+        }
+        Some(
+            unsafe { CStr::from_ptr(value.cast()) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+
     fn ensure_current(&self) -> Result<(), LayerShikaError> {
         if !self.context.is_current() {
             self.context.make_current(&self.surface).map_err(|e| {
@@ -181,6 +331,8 @@ unsafe impl OpenGLInterface for EGLContext {
             .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
     }
 
+    /// Swaps without damage rects, which damages the whole surface, so the first frame after a
+    /// resize is always fully redrawn. `FemtoVG` has no partial rendering to pass rects for.
     fn swap_buffers(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         self.surface.swap_buffers(&self.context).map_err(|e| {
             LayerShikaError::EGLContextCreation(format!("Failed to swap buffers: {e}")).into()
@@ -201,3 +353,61 @@ unsafe impl OpenGLInterface for EGLContext {
         self.context.display().get_proc_address(name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_desktop_and_embedded_versions() {
+        assert_eq!(
+            GlVersion::parse("4.6 (Compatibility Profile) Mesa 24.0.5"),
+            Some(GlVersion {
+                embedded: false,
+                major: 4,
+                minor: 6
+            })
+        );
+        assert_eq!(
+            GlVersion::parse("OpenGL ES 3.2 Mesa 24.0.5"),
+            Some(GlVersion {
+                embedded: true,
+                major: 3,
+                minor: 2
+            })
+        );
+        assert_eq!(
+            GlVersion::parse("OpenGL ES-CM 1.1"),
+            Some(GlVersion {
+                embedded: true,
+                major: 1,
+                minor: 1
+            })
+        );
+        assert_eq!(GlVersion::parse("unknown"), None);
+    }
+
+    #[test]
+    fn names_the_missing_requirement() {
+        let version = |version| GlVersion::parse(version).expect("valid GL version string");
+        assert_eq!(version("3.3.0 NVIDIA 550.78").missing_requirement(""), None);
+        assert_eq!(
+            version("2.1 Mesa 24.0.5").missing_requirement(""),
+            Some("OpenGL 3.0".to_owned())
+        );
+        assert_eq!(version("OpenGL ES 3.0").missing_requirement(""), None);
+        assert_eq!(
+            version("OpenGL ES 2.0").missing_requirement("GL_OES_mapbuffer"),
+            Some("GL_OES_vertex_array_object".to_owned())
+        );
+        assert_eq!(
+            version("OpenGL ES 2.0")
+                .missing_requirement("GL_OES_mapbuffer GL_OES_vertex_array_object"),
+            None
+        );
+        assert_eq!(
+            version("OpenGL ES-CM 1.1").missing_requirement(""),
+            Some("OpenGL ES 2.0".to_owned())
+        );
+    }
+}
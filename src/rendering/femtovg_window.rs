@@ -1,27 +1,85 @@
-use crate::errors::LayerShikaError;
+#[cfg(feature = "unstable-slint-internals")]
+use crate::windowing::color_scheme::ColorScheme;
+use crate::{
+    errors::LayerShikaError,
+    rendering::egl_context::{EGLContext, SharedGlContext},
+};
+#[cfg(feature = "unstable-slint-internals")]
+use i_slint_core::{
+    items::ColorScheme as SlintColorScheme, window::WindowAdapterInternal, InternalToken, Property,
+};
+use i_slint_renderer_femtovg::FemtoVGRendererExt;
 use log::info;
+#[cfg(feature = "headless")]
+use slint::{
+    platform::software_renderer::{PremultipliedRgbaColor, SoftwareRenderer},
+    SharedPixelBuffer,
+};
 use slint::{
-    platform::{femtovg_renderer::FemtoVGRenderer, Renderer, WindowAdapter, WindowEvent},
-    PhysicalSize, Window, WindowSize,
+    platform::{
+        femtovg_renderer::FemtoVGRenderer, Renderer, WindowAdapter, WindowEvent, WindowProperties,
+    },
+    LogicalSize, PhysicalSize, Window, WindowSize,
 };
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+#[cfg(feature = "unstable-slint-internals")]
+use std::pin::Pin;
 use std::rc::{Rc, Weak};
 
+#[derive(Clone, Copy)]
 pub enum RenderState {
     Clean,
     Dirty,
 }
 
+/// What draws the window's frames.
+enum WindowRenderer {
+    /// `FemtoVG` on the OpenGL context of a Wayland surface.
+    FemtoVG(FemtoVGRenderer),
+    /// Slint's software renderer, into the last frame kept in memory, for surfaces driven
+    /// without a compositor or GPU.
+    #[cfg(feature = "headless")]
+    Software {
+        renderer: SoftwareRenderer,
+        frame: RefCell<SharedPixelBuffer<PremultipliedRgbaColor>>,
+    },
+}
+
 pub struct FemtoVGWindow {
     window: Window,
-    renderer: FemtoVGRenderer,
+    renderer: WindowRenderer,
     render_state: Cell<RenderState>,
     size: Cell<PhysicalSize>,
     scale_factor: Cell<f32>,
+    /// A property rather than a plain cell, so that bindings reading it are re-evaluated when
+    /// the desktop's preference changes.
+    #[cfg(feature = "unstable-slint-internals")]
+    color_scheme: Pin<Box<Property<SlintColorScheme>>>,
+    preferred_size: Cell<LogicalSize>,
+    gl_context: RefCell<Option<SharedGlContext>>,
 }
 
 impl FemtoVGWindow {
-    pub fn new(renderer: FemtoVGRenderer) -> Rc<Self> {
+    /// Creates a window whose renderer has no OpenGL context yet, so it can't draw until
+    /// [`Self::attach_context`] succeeds. Components can already be created and laid out.
+    pub fn new_suspended() -> Rc<Self> {
+        Self::new(WindowRenderer::FemtoVG(
+            FemtoVGRenderer::new_without_context(),
+        ))
+    }
+
+    /// Creates a window drawn by Slint's software renderer into memory, see [`Self::frame`].
+    /// It takes no OpenGL context.
+    #[cfg(feature = "headless")]
+    pub fn new_software() -> Rc<Self> {
+        Self::new(WindowRenderer::Software {
+            renderer: SoftwareRenderer::new(),
+            frame: RefCell::new(SharedPixelBuffer::new(0, 0)),
+        })
+    }
+
+    fn new(renderer: WindowRenderer) -> Rc<Self> {
         Rc::new_cyclic(|weak_self| {
             let window = Window::new(Weak::clone(weak_self) as Weak<dyn WindowAdapter>);
             Self {
@@ -30,22 +88,93 @@ impl FemtoVGWindow {
                 render_state: Cell::new(RenderState::Clean),
                 size: Cell::new(PhysicalSize::default()),
                 scale_factor: Cell::new(1.),
+                #[cfg(feature = "unstable-slint-internals")]
+                color_scheme: Box::pin(Property::new(SlintColorScheme::Unknown)),
+                preferred_size: Cell::new(LogicalSize::default()),
+                gl_context: RefCell::new(None),
             }
         })
     }
 
+    /// Checks that `context` can drive the renderer and starts rendering through it.
+    #[cfg_attr(
+        not(feature = "headless"),
+        allow(clippy::infallible_destructuring_match)
+    )]
+    pub fn attach_context(&self, context: EGLContext) -> Result<(), LayerShikaError> {
+        let renderer = match &self.renderer {
+            WindowRenderer::FemtoVG(renderer) => renderer,
+            #[cfg(feature = "headless")]
+            WindowRenderer::Software { .. } => {
+                return Err(LayerShikaError::Rendering(
+                    "The software renderer draws without an OpenGL context".into(),
+                ))
+            }
+        };
+        context.check_renderer_requirements()?;
+        let shared = context.shared();
+        // FemtoVG panics instead of returning an error when its shaders cannot be set up, which
+        // the driver check above rules out.
+        renderer
+            .set_opengl_context(context)
+            .map_err(|e| LayerShikaError::FemtoVGRendererCreation(e.to_string()))?;
+        *self.gl_context.borrow_mut() = Some(shared);
+        Ok(())
+    }
+
+    /// Releases the renderer's GL resources and the context, e.g. before the surface it draws to
+    /// is destroyed. Rendering fails until another context is attached.
+    pub fn detach_context(&self) -> Result<(), LayerShikaError> {
+        self.gl_context.borrow_mut().take();
+        match &self.renderer {
+            WindowRenderer::FemtoVG(renderer) => renderer
+                .clear_opengl_context()
+                .map_err(|e| LayerShikaError::Rendering(format!("Failed to release context: {e}"))),
+            #[cfg(feature = "headless")]
+            WindowRenderer::Software { .. } => Ok(()),
+        }
+    }
+
+    /// The context the renderer draws with, `None` until one is attached.
+    pub fn gl_context(&self) -> Option<SharedGlContext> {
+        self.gl_context.borrow().clone()
+    }
+
     pub fn render_frame_if_dirty(&self) -> Result<(), LayerShikaError> {
         if matches!(
             self.render_state.replace(RenderState::Clean),
             RenderState::Dirty
         ) {
-            self.renderer
-                .render()
-                .map_err(|e| LayerShikaError::Rendering(format!("Error rendering frame: {e}")))?;
+            match &self.renderer {
+                WindowRenderer::FemtoVG(renderer) => renderer.render().map_err(|e| {
+                    LayerShikaError::Rendering(format!("Error rendering frame: {e}"))
+                })?,
+                #[cfg(feature = "headless")]
+                WindowRenderer::Software { renderer, frame } => {
+                    let size = self.size();
+                    let mut buffer = SharedPixelBuffer::new(size.width, size.height);
+                    renderer.render(buffer.make_mut_slice(), size.width as usize);
+                    *frame.borrow_mut() = buffer;
+                }
+            }
         }
         Ok(())
     }
 
+    /// The last frame drawn by the software renderer, with premultiplied alpha. `None` for a
+    /// window rendering through OpenGL.
+    #[cfg(feature = "headless")]
+    pub fn frame(&self) -> Option<SharedPixelBuffer<PremultipliedRgbaColor>> {
+        match &self.renderer {
+            WindowRenderer::FemtoVG(_) => None,
+            WindowRenderer::Software { frame, .. } => Some(frame.borrow().clone()),
+        }
+    }
+
+    pub const fn is_dirty(&self) -> bool {
+        matches!(self.render_state.get(), RenderState::Dirty)
+    }
+
     pub fn set_scale_factor(&self, scale_factor: f32) {
         info!("Setting scale factor to {}", scale_factor);
         self.scale_factor.set(scale_factor);
@@ -53,9 +182,38 @@ impl FemtoVGWindow {
             .dispatch_event(WindowEvent::ScaleFactorChanged { scale_factor });
     }
 
-    pub fn scale_factor(&self) -> f32 {
+    pub const fn scale_factor(&self) -> f32 {
         self.scale_factor.get()
     }
+
+    /// Makes the font at `path` available to Slint, for all windows of the process.
+    pub fn register_font_from_path(&self, path: &Path) -> Result<(), LayerShikaError> {
+        info!("Registering font {}", path.display());
+        self.renderer().register_font_from_path(path).map_err(|e| {
+            LayerShikaError::InvalidInput(format!(
+                "Failed to register font {}: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    pub fn register_font_from_memory(&self, data: &'static [u8]) -> Result<(), LayerShikaError> {
+        self.renderer()
+            .register_font_from_memory(data)
+            .map_err(|e| LayerShikaError::InvalidInput(format!("Failed to register font: {e}")))
+    }
+
+    /// Size the component's layout prefers, updated by Slint whenever it changes.
+    pub const fn preferred_size(&self) -> LogicalSize {
+        self.preferred_size.get()
+    }
+
+    /// Sets the color scheme reported to Slint styles, `ColorScheme::Unknown` until the desktop's
+    /// preference is known or one is forced.
+    #[cfg(feature = "unstable-slint-internals")]
+    pub fn set_color_scheme(&self, color_scheme: ColorScheme) {
+        self.color_scheme.as_ref().set(color_scheme.into());
+    }
 }
 
 impl WindowAdapter for FemtoVGWindow {
@@ -64,7 +222,11 @@ impl WindowAdapter for FemtoVGWindow {
     }
 
     fn renderer(&self) -> &dyn Renderer {
-        &self.renderer
+        match &self.renderer {
+            WindowRenderer::FemtoVG(renderer) => renderer,
+            #[cfg(feature = "headless")]
+            WindowRenderer::Software { renderer, .. } => renderer,
+        }
     }
 
     fn size(&self) -> PhysicalSize {
@@ -81,6 +243,25 @@ impl WindowAdapter for FemtoVGWindow {
     fn request_redraw(&self) {
         self.render_state.set(RenderState::Dirty);
     }
+
+    fn update_window_properties(&self, properties: WindowProperties<'_>) {
+        self.preferred_size
+            .set(properties.layout_constraints().preferred);
+    }
+
+    #[cfg(feature = "unstable-slint-internals")]
+    fn internal(&self, _: InternalToken) -> Option<&dyn WindowAdapterInternal> {
+        Some(self)
+    }
+}
+
+/// Slint only asks window adapters for the color scheme through its private
+/// `WindowAdapterInternal`, which is why this needs the `unstable-slint-internals` feature.
+#[cfg(feature = "unstable-slint-internals")]
+impl WindowAdapterInternal for FemtoVGWindow {
+    fn color_scheme(&self) -> SlintColorScheme {
+        self.color_scheme.as_ref().get()
+    }
 }
 
 impl core::ops::Deref for FemtoVGWindow {
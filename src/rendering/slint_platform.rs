@@ -1,23 +1,83 @@
+use crate::errors::LayerShikaError;
+use log::info;
 use slint::{
     platform::{Platform, WindowAdapter},
     PlatformError,
 };
-use std::rc::Rc;
-
-use super::femtovg_window::FemtoVGWindow;
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+};
 
-pub struct CustomSlintPlatform {
-    window: Rc<FemtoVGWindow>,
+thread_local! {
+    /// Slint keeps one platform per thread, so it is installed by the first `WindowingSystem`
+    /// of a thread and shared by all later ones on it.
+    static PLATFORM_INSTALLED: Cell<bool> = const { Cell::new(false) };
+    /// Every live window created on this thread, in creation order.
+    static WINDOWS: RefCell<Vec<Weak<dyn WindowAdapter>>> = const { RefCell::new(Vec::new()) };
+    /// Window that adapters requested while a component is being created are routed to.
+    static CREATION_TARGET: RefCell<Option<Rc<dyn WindowAdapter>>> = const { RefCell::new(None) };
 }
 
+/// The Slint platform shared by all surfaces. Slint asks it for a window adapter lazily, when a
+/// component is instantiated, so the window a component belongs to is selected by creating the
+/// component inside [`Self::create_in`].
+pub struct CustomSlintPlatform;
+
 impl CustomSlintPlatform {
-    pub fn new(window: Rc<FemtoVGWindow>) -> Self {
-        Self { window }
+    /// Installs the platform unless a previous `WindowingSystem` on this thread already did.
+    pub fn install() -> Result<(), LayerShikaError> {
+        if PLATFORM_INSTALLED.replace(true) {
+            info!("Slint platform already initialized, reusing it for the new window");
+            return Ok(());
+        }
+
+        slint::platform::set_platform(Box::new(Self)).map_err(|e| {
+            PLATFORM_INSTALLED.set(false);
+            LayerShikaError::PlatformSetup(format!(
+                "platform already initialized ({e:?}): another Slint backend was set up on this \
+                 thread; create layer-shika windows before any other Slint window and do not \
+                 call slint::platform::set_platform yourself"
+            ))
+        })
+    }
+
+    pub fn register_window<W: WindowAdapter + 'static>(window: &Rc<W>) {
+        WINDOWS.with_borrow_mut(|windows| {
+            windows.retain(|window| window.strong_count() > 0);
+            windows.push(Rc::downgrade(window) as Weak<dyn WindowAdapter>);
+        });
+    }
+
+    /// Runs `create` with every window adapter Slint requests in the meantime routed to `window`.
+    pub fn create_in<W: WindowAdapter + 'static, R>(
+        window: &Rc<W>,
+        create: impl FnOnce() -> R,
+    ) -> R {
+        let previous = CREATION_TARGET.replace(Some(Rc::clone(window) as Rc<dyn WindowAdapter>));
+        let result = create();
+        CREATION_TARGET.set(previous);
+        result
+    }
+
+    fn only_window() -> Option<Rc<dyn WindowAdapter>> {
+        WINDOWS.with_borrow(|windows| {
+            let mut live = windows.iter().filter_map(Weak::upgrade);
+            let window = live.next()?;
+            live.next().is_none().then_some(window)
+        })
     }
 }
 
 impl Platform for CustomSlintPlatform {
-    fn create_window_adapter(&self) -> Result<Rc<(dyn WindowAdapter + 'static)>, PlatformError> {
-        Result::Ok(Rc::clone(&self.window) as Rc<dyn WindowAdapter>)
+    fn create_window_adapter(&self) -> Result<Rc<dyn WindowAdapter + 'static>, PlatformError> {
+        CREATION_TARGET
+            .with_borrow(Clone::clone)
+            .or_else(Self::only_window)
+            .ok_or_else(|| {
+                PlatformError::Other(
+                    "Cannot tell which layer-shika surface a new Slint window belongs to".into(),
+                )
+            })
     }
 }
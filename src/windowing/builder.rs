@@ -1,12 +1,36 @@
-use slint_interpreter::ComponentDefinition;
+use glutin::config::ConfigTemplateBuilder;
+use slint::{GraphicsAPI, LogicalSize, PhysicalSize, RenderingState};
+use slint_interpreter::{ComponentDefinition, ComponentInstance, Value};
 use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::{
     zwlr_layer_shell_v1::{self},
     zwlr_layer_surface_v1::{Anchor, KeyboardInteractivity},
 };
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+use wayland_client::Connection;
+use xkbcommon::xkb::Keysym;
+
+use crate::{errors::LayerShikaError, rendering::egl_context::SharedGlContext};
 
-use crate::errors::LayerShikaError;
+use super::{
+    config::{ConfigureBehavior, FontSource, HeightMode, OutputLossBehavior, WindowConfig},
+    input::{FilterAction, InputEvent},
+    keyboard::Modifiers,
+    language,
+    layouts::LayoutSet,
+    outputs::OutputInfo,
+    snapshot::ConfigSnapshot,
+    source::ComponentSource,
+    WindowingSystem,
+};
 
-use super::{config::WindowConfig, WindowingSystem};
+#[cfg(feature = "unstable-slint-internals")]
+use super::color_scheme::ColorScheme;
+#[cfg(feature = "headless")]
+use super::{headless::HeadlessWindowingSystem, OptionalProtocols};
 
 pub struct WindowingSystemBuilder {
     config: WindowConfig,
@@ -33,6 +57,27 @@ impl WindowingSystemBuilder {
         self
     }
 
+    /// Sizes the surface to the preferred height of the component's layout instead of a fixed
+    /// height, resizing it whenever the content changes, e.g. a notification whose text wraps.
+    /// `with_height` only applies until the first layout. The new height is requested from the
+    /// compositor and takes effect with its next configure. A positive exclusive zone follows the
+    /// height, so the reserved space always matches the surface; a zone of 0 or -1 stays as it
+    /// is, so set a positive one with [`Self::with_exclusive_zone`] to reserve space.
+    #[must_use]
+    pub const fn with_auto_height(mut self) -> Self {
+        self.config.height_mode = HeightMode::Auto;
+        self
+    }
+
+    /// Gives the surface a fixed width instead of the full output width, e.g. for a bar anchored
+    /// to a single corner. Ignored while the surface is anchored to both left and right, where
+    /// the compositor stretches it across the output.
+    #[must_use]
+    pub const fn with_width(mut self, width: u32) -> Self {
+        self.config.width = Some(width);
+        self
+    }
+
     #[must_use]
     pub const fn with_layer(mut self, layer: zwlr_layer_shell_v1::Layer) -> Self {
         self.config.layer = layer;
@@ -51,6 +96,42 @@ impl WindowingSystemBuilder {
         self
     }
 
+    /// Creates an identical surface on every output, e.g. one bar per monitor, instead of a
+    /// single one. Each gets its own component instance, see
+    /// [`WindowingSystem::component_instances`]; runtime changes like
+    /// [`WindowingSystem::set_anchor`] apply to all of them. Callbacks, input filters and the
+    /// initial focus only apply to the surface on the first output, or the one picked by
+    /// [`Self::with_output_matcher`], and `run` returns once that surface closes. The surfaces
+    /// share the seat's input devices. An output plugged in later gets a surface with the first
+    /// one's current settings and property values; hot reload and the portal color scheme only
+    /// reach the surfaces created at startup.
+    #[must_use]
+    pub const fn on_all_outputs(mut self) -> Self {
+        self.config.all_outputs = true;
+        self
+    }
+
+    /// Puts the surface on the first output `matcher` accepts instead of the first one advertised,
+    /// e.g. `|output| output.model.contains("U2720Q")` to follow a monitor whatever connector it is
+    /// plugged into. Building fails if no output matches. When the surface's output goes away,
+    /// it moves to another matching output, or with [`Self::wait_for_output_return`] comes back
+    /// on the next matching one plugged in, unless `on_closed` decides.
+    #[must_use]
+    pub fn with_output_matcher(mut self, matcher: impl Fn(&OutputInfo) -> bool + 'static) -> Self {
+        self.config.output_matcher = Some(Box::new(matcher));
+        self
+    }
+
+    /// Anchors the surface to all four edges with an exclusive zone of -1, e.g. for a wallpaper
+    /// or an overlay: it covers the whole output, ignoring the configured height, and is not
+    /// moved by other surfaces' exclusive zones.
+    #[must_use]
+    pub const fn fullscreen(mut self) -> Self {
+        self.config.anchor = Anchor::all();
+        self.config.exclusive_zone = -1;
+        self
+    }
+
     #[must_use]
     pub const fn with_keyboard_interactivity(
         mut self,
@@ -60,37 +141,564 @@ impl WindowingSystemBuilder {
         self
     }
 
+    /// Whether the surface reacts to mice and touchpads, independently of the input region.
+    /// Enabled by default; disable it e.g. for a touch-only kiosk overlay.
+    #[must_use]
+    pub const fn with_pointer_enabled(mut self, enabled: bool) -> Self {
+        self.config.pointer_enabled = enabled;
+        self
+    }
+
+    /// Whether the surface reacts to touchscreens, independently of the input region. Enabled by
+    /// default; the first touch point is delivered to Slint like a left-button pointer.
+    #[must_use]
+    pub const fn with_touch_enabled(mut self, enabled: bool) -> Self {
+        self.config.touch_enabled = enabled;
+        self
+    }
+
     #[must_use]
     pub const fn with_exclusive_zone(mut self, zone: i32) -> Self {
         self.config.exclusive_zone = zone;
         self
     }
 
+    /// Caps a positive exclusive zone at `fraction` (in `(0, 1]`) of the output extent along the
+    /// anchored edge, e.g. `0.1` to never reserve more than a tenth of the output height for a
+    /// top or bottom bar, however large its content grows.
+    #[must_use]
+    pub const fn with_max_exclusive_fraction(mut self, fraction: f32) -> Self {
+        self.config.max_exclusive_fraction = Some(fraction);
+        self
+    }
+
+    /// Stops configure events from re-sending the exclusive zone, anchor and margin, for
+    /// surfaces that manage their reserved space at runtime and must not have it reset.
+    #[must_use]
+    pub const fn keep_layer_state_on_configure(mut self) -> Self {
+        self.config.configure_behavior = ConfigureBehavior::KeepLayerState;
+        self
+    }
+
+    /// Keeps running when the compositor closes the surface because its output went away, and
+    /// re-creates the surface once an output with the same name is plugged in again, e.g. a
+    /// monitor switched off and on, or one [`Self::with_output_matcher`] accepts. `on_closed`
+    /// still takes precedence when registered.
+    #[must_use]
+    pub const fn wait_for_output_return(mut self) -> Self {
+        self.config.output_loss_behavior = OutputLossBehavior::WaitForReturn;
+        self
+    }
+
     #[must_use]
     pub fn with_namespace(mut self, namespace: String) -> Self {
         self.config.namespace = namespace;
         self
     }
 
+    /// Appends `-suffix` to the namespace, e.g. `layer-shika-clock`, so that several instances
+    /// can be told apart by compositor rules matching on the namespace.
+    #[must_use]
+    pub fn with_namespace_suffix(mut self, suffix: &str) -> Self {
+        self.config.namespace_suffix = Some(suffix.to_owned());
+        self
+    }
+
+    /// Zoom applied to the component on top of the output scale, see
+    /// [`Self::follow_output_scale`].
     #[must_use]
     pub const fn with_scale_factor(mut self, scale_factor: f32) -> Self {
         self.config.scale_factor = scale_factor;
         self
     }
 
+    /// Keeps only a `thickness` pixel strip of the surface on screen, along the single edge it is
+    /// anchored to, by pushing the rest off-screen with a negative margin. The full surface is
+    /// revealed while the pointer is over it and hidden again when the pointer leaves.
+    #[must_use]
+    pub const fn with_hot_edge(mut self, thickness: u32) -> Self {
+        self.config.hot_edge = Some(thickness);
+        self
+    }
+
+    /// Declares the whole surface opaque, following its size, so the compositor can skip drawing
+    /// whatever lies below, e.g. for a solid bar. Only use it when no pixel is translucent.
+    #[must_use]
+    pub const fn with_opaque(mut self, opaque: bool) -> Self {
+        self.config.opaque = opaque;
+        self
+    }
+
+    /// Groups surfaces on the same layer: higher hints are meant to stack above lower ones.
+    ///
+    /// Layer-shell cannot reorder surfaces within a layer, and compositors generally put the most
+    /// recently mapped surface on top. When a surface is created below an existing higher hint,
+    /// a warning is logged and the higher surfaces of this thread re-create their layer surface
+    /// from their event loop, lowest hint first, to get back on top. Creating surfaces in
+    /// ascending hint order avoids that. Only the surface on the first output takes part.
+    #[must_use]
+    pub const fn with_z_hint(mut self, z_hint: i32) -> Self {
+        self.config.z_hint = z_hint;
+        self
+    }
+
+    /// Renders into a buffer `render_scale` times the surface size (e.g. `0.5` for half
+    /// resolution) and lets the compositor scale it to the surface through `wp_viewporter`.
+    /// Trades sharpness for GPU time; ignored with a warning if the compositor lacks viewporter.
+    #[must_use]
+    pub const fn with_render_scale(mut self, render_scale: f32) -> Self {
+        self.config.render_scale = render_scale;
+        self
+    }
+
+    /// Renders the buffer at the scale of the output the surface is on, re-rendering whenever it
+    /// changes, e.g. when a laptop is docked to a high-density monitor. Fractional scales such as
+    /// 1.5 are used when the compositor supports `wp_fractional_scale_v1` and `wp_viewporter`,
+    /// otherwise the integer output scale. Sizes stay in
+    /// surface coordinates and [`Self::with_scale_factor`] becomes a plain zoom on top. Enabled
+    /// by default; disable it to render at exactly the configured scale factor.
+    #[must_use]
+    pub const fn follow_output_scale(mut self, follow: bool) -> Self {
+        self.config.follow_output_scale = follow;
+        self
+    }
+
+    /// Compares the size the compositor grants in the first configure to the requested one and
+    /// logs a warning with the likely cause when they differ, most often an anchor combination
+    /// that stretches the surface or keeps it from spanning the output.
+    #[must_use]
+    pub const fn with_configure_diagnostics(mut self) -> Self {
+        self.config.configure_diagnostics = true;
+        self
+    }
+
+    /// Closes the surface `timeout` after it is first mapped, as notification toasts do. The
+    /// countdown is paused while the pointer is over the surface and restarts when it leaves.
+    #[must_use]
+    pub const fn with_auto_close(mut self, timeout: Duration) -> Self {
+        self.config.auto_close = Some(timeout);
+        self
+    }
+
+    /// Adjusts the EGL config template (alpha, samples, depth, ...) before a config is selected.
+    /// If no config matches the adjusted template, `build` fails with the EGL error.
+    #[must_use]
+    pub fn with_egl_config(
+        mut self,
+        configure: impl FnOnce(ConfigTemplateBuilder) -> ConfigTemplateBuilder + 'static,
+    ) -> Self {
+        self.config.egl_config = Some(Box::new(configure));
+        self
+    }
+
+    /// Creates the surface's OpenGL context in the share group of `context`, taken from another
+    /// surface's `WindowingSystem::gl_context`, so that GL objects such as textures can be shared
+    /// between the surfaces instead of being uploaded once per surface. Both contexts use the
+    /// same EGL display.
+    #[must_use]
+    pub fn with_shared_gl_context(mut self, context: SharedGlContext) -> Self {
+        self.config.shared_gl_context = Some(context);
+        self
+    }
+
+    /// Calls `callback` around every frame Slint renders, with the surface's OpenGL context
+    /// current, so custom GL can be drawn underneath (`BeforeRendering`) or on top
+    /// (`AfterRendering`) of the component. `RenderingSetup` and `RenderingTeardown` bracket the
+    /// lifetime of each context, e.g. to create and delete shaders.
+    #[must_use]
+    pub fn with_rendering_notifier(
+        mut self,
+        callback: impl FnMut(RenderingState, &GraphicsAPI<'_>) + 'static,
+    ) -> Self {
+        self.config.rendering_notifier = Some(Box::new(callback));
+        self
+    }
+
+    /// Postpones creating the EGL context and `FemtoVG` renderer, the slowest part of `build`,
+    /// until the first frame is drawn, e.g. for surfaces that may never be shown. Driver problems
+    /// then surface as errors from `run` instead of `build`.
+    #[must_use]
+    pub const fn defer_renderer_creation(mut self) -> Self {
+        self.config.defer_renderer = true;
+        self
+    }
+
+    /// Reserved for synchronizing buffers through `wp_linux_drm_syncobj_v1`, which is not wired
+    /// up yet: frames are always presented through EGL, which leaves synchronization to the
+    /// driver's implicit sync. Enabling it currently only logs whether the compositor would
+    /// support explicit sync.
+    #[must_use]
+    pub const fn with_explicit_sync(mut self, explicit_sync: bool) -> Self {
+        self.config.explicit_sync = explicit_sync;
+        self
+    }
+
+    /// Forces the light/dark preference seen by Slint styles, which otherwise use their default
+    /// scheme. Slint only reads it through its private API, hence the
+    /// `unstable-slint-internals` feature, which ties the crate to the exact Slint version.
+    #[cfg(feature = "unstable-slint-internals")]
+    #[must_use]
+    pub const fn with_color_scheme(mut self, color_scheme: ColorScheme) -> Self {
+        self.config.color_scheme = color_scheme;
+        self.config.follow_portal_color_scheme = false;
+        self
+    }
+
+    /// Follows the `color-scheme` of the xdg-desktop-portal settings instead of a fixed scheme,
+    /// including changes while running. The portal is watched from a thread holding a session
+    /// bus connection, which only notices the surfaces are gone with the next change and may
+    /// outlive the `WindowingSystem` until then.
+    #[cfg(feature = "unstable-slint-internals")]
+    #[must_use]
+    pub const fn with_color_scheme_from_portal(mut self) -> Self {
+        self.config.follow_portal_color_scheme = true;
+        self
+    }
+
+    /// Selects the language `@tr` strings are translated to, e.g. `"de"` or `"pt_BR"`, by setting
+    /// `LANGUAGE` before the component is compiled and created. Translations go through gettext,
+    /// so this needs Slint's `gettext` feature enabled and the text domain bound by the
+    /// application; Slint 1.7 has no bundled translations. The environment is written once per
+    /// process, before this crate starts any thread, so later selections are ignored with a
+    /// warning; build before the application starts threads of its own. `WindowState::set_language`
+    /// only re-translates at runtime, see there.
+    #[must_use]
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.config.language = Some(language.to_owned());
+        self
+    }
+
+    /// Sets the component to show. This or [`Self::with_component_path`] is required, but like
+    /// every other setting it can be given at any point before [`Self::build`], which fails
+    /// with [`LayerShikaError::WindowConfiguration`] if it is missing.
     #[must_use]
     pub fn with_component_definition(mut self, component: ComponentDefinition) -> Self {
         self.config.component_definition = Some(component);
+        self.config.component_source = None;
+        self
+    }
+
+    /// Compiles the component from a `.slint` file when building, instead of taking a ready
+    /// definition. Imports resolve relative to the file. The file must export a single component
+    /// unless one is picked with [`Self::with_component_name`]; compilation errors make
+    /// [`Self::build`] fail with [`LayerShikaError::SlintComponentCreation`].
+    #[must_use]
+    pub fn with_component_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.config.component_source = Some(ComponentSource::Path(path.as_ref().to_path_buf()));
+        self.config.component_definition = None;
+        self
+    }
+
+    /// Compiles the component from inline Slint markup when building, e.g. for a small bar or a
+    /// test. `name` picks the exported component like [`Self::with_component_name`]. Relative
+    /// imports resolve against the working directory and the paths from
+    /// [`Self::with_include_paths`]; compilation errors make [`Self::build`] fail with
+    /// [`LayerShikaError::SlintComponentCreation`].
+    #[must_use]
+    pub fn with_component_source(mut self, source: &str, name: Option<&str>) -> Self {
+        self.config.component_source = Some(ComponentSource::Inline(source.to_owned()));
+        self.config.component_name = name.map(ToOwned::to_owned);
+        self.config.component_definition = None;
+        self
+    }
+
+    /// Picks the exported component to show from markup exporting several.
+    #[must_use]
+    pub fn with_component_name(mut self, name: &str) -> Self {
+        self.config.component_name = Some(name.to_owned());
+        self
+    }
+
+    /// Recompiles the component whenever a `.slint` file in the directory of the one from
+    /// [`Self::with_component_path`] changes, and swaps the new component in without restarting.
+    /// Property values changed at runtime are carried over, but callbacks set on the old instance
+    /// must be set again from [`Self::on_component_reloaded`]. Markup that fails to compile is
+    /// logged and the current component kept. Building fails without a component path.
+    #[cfg(feature = "hot-reload")]
+    #[must_use]
+    pub const fn with_hot_reload(mut self) -> Self {
+        self.config.hot_reload = true;
+        self
+    }
+
+    /// Called with the new component after a hot reload replaced it, e.g. to register its
+    /// callbacks again.
+    #[must_use]
+    pub fn on_component_reloaded(
+        mut self,
+        callback: impl Fn(&ComponentInstance) + 'static,
+    ) -> Self {
+        self.config.callbacks.on_component_reloaded = Some(Box::new(callback));
+        self
+    }
+
+    /// Directories searched for imports of markup compiled from a path or source.
+    #[must_use]
+    pub fn with_include_paths(mut self, include_paths: Vec<PathBuf>) -> Self {
+        self.config.include_paths = include_paths;
+        self
+    }
+
+    /// Applies a configuration captured with `WindowingSystem::current_config`, overriding the
+    /// layer, anchor, margin, height, exclusive zone, keyboard interactivity, scale and namespace.
+    #[must_use]
+    pub fn with_config_snapshot(mut self, snapshot: &ConfigSnapshot) -> Self {
+        self.config.apply_snapshot(snapshot);
+        self
+    }
+
+    /// Named layout presets to switch between at runtime with `WindowingSystem::apply_layout`.
+    #[must_use]
+    pub fn with_layouts(mut self, layouts: LayoutSet) -> Self {
+        self.config.layouts = layouts;
+        self
+    }
+
+    /// Registers a font file (e.g. an icon font) with Slint before the component is shown, so
+    /// it doesn't depend on the font being installed. Can be called repeatedly.
+    #[must_use]
+    pub fn with_font(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.fonts.push(FontSource::Path(path.into()));
+        self
+    }
+
+    /// Like [`Self::with_font`], for font data embedded in the binary with `include_bytes!`.
+    #[must_use]
+    pub fn with_font_bytes(mut self, data: &'static [u8]) -> Self {
+        self.config.fonts.push(FontSource::Memory(data));
+        self
+    }
+
+    /// Sets a property on the component instance right after it is created, before it is shown.
+    /// Can be called repeatedly; later values for the same property win.
+    #[must_use]
+    pub fn with_property(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.config.properties.push((name.into(), value));
+        self
+    }
+
+    /// Invokes the component's public function or callback `name` right after the component is
+    /// shown, and again whenever the component is re-created.
+    /// Slint has no API to focus an element by id from outside, so the component exposes one,
+    /// e.g. `public function focus-search() { search.focus(); }`.
+    #[must_use]
+    pub fn with_initial_focus(mut self, name: impl Into<String>) -> Self {
+        self.config.initial_focus = Some(name.into());
+        self
+    }
+
+    /// Connects to the compositor listening on `path` instead of the one named by
+    /// `WAYLAND_DISPLAY`/`WAYLAND_SOCKET`. Relative paths are resolved against `XDG_RUNTIME_DIR`,
+    /// like socket names in `WAYLAND_DISPLAY`.
+    #[must_use]
+    pub fn with_wayland_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.wayland_socket = Some(path.into());
+        self
+    }
+
+    /// Reuses a connection the embedder already holds instead of opening a new one; takes
+    /// precedence over [`Self::with_wayland_socket`].
+    ///
+    /// The windowing system creates its own event queue on this connection and reads the socket
+    /// from its event loop. The embedder keeps ownership of any other queues it created and must
+    /// only dispatch those (e.g. with `dispatch_pending` after the loop has read), never the
+    /// windowing system's queue.
+    #[must_use]
+    pub fn with_connection(mut self, connection: Rc<Connection>) -> Self {
+        self.config.connection = Some(connection);
+        self
+    }
+
+    /// Called after every size change has been applied to the surface, with the size of the
+    /// buffer rendered for it in physical pixels and its size in logical units at the current
+    /// scale factor. Configures that leave both sizes unchanged are not reported.
+    #[must_use]
+    pub fn on_resized(mut self, callback: impl Fn(PhysicalSize, LogicalSize) + 'static) -> Self {
+        self.config.callbacks.on_resized = Some(Box::new(callback));
+        self
+    }
+
+    /// Called once, with the configured and the output-advertised scale, when the output the
+    /// surface is on reports a scale that differs from [`Self::with_scale_factor`].
+    ///
+    /// Only called with `follow_output_scale(false)`. While following the output scale, the
+    /// default, the buffer is rendered at the output's scale and the scale factor is a zoom on
+    /// top, so the two are expected to differ; see [`Self::on_scale_conflict`] instead.
+    #[must_use]
+    pub fn on_scale_mismatch(mut self, callback: impl Fn(f32, f32) + 'static) -> Self {
+        self.config.callbacks.on_scale_mismatch = Some(Box::new(callback));
+        self
+    }
+
+    /// Called with the render scale and the output scale whenever the surface lands on an output
+    /// whose scale differs from the number of buffer pixels rendered per surface pixel (see
+    /// [`Self::with_render_scale`]), which makes the compositor resample the buffer and text look
+    /// blurry. Fires again for each new conflicting output scale.
+    #[must_use]
+    pub fn on_scale_conflict(mut self, callback: impl Fn(f32, i32) + 'static) -> Self {
+        self.config.callbacks.on_scale_conflict = Some(Box::new(callback));
+        self
+    }
+
+    /// Called for every key press (`true`) and release (`false`) with the raw xkb keysym and the
+    /// active modifiers, for keybindings that don't map onto Slint's text-based key events.
+    #[must_use]
+    pub fn on_key(mut self, callback: impl Fn(Keysym, Modifiers, bool) + 'static) -> Self {
+        self.config.callbacks.on_key = Some(Box::new(callback));
+        self
+    }
+
+    /// Called with `false` when the compositor stops presenting the surface (minimized output,
+    /// fully occluded, ...) and with `true` once it presents it again, after frame callbacks have
+    /// stalled or resumed for about a second. Use it to pause work nobody can see.
+    #[must_use]
+    pub fn on_visibility_changed(mut self, callback: impl Fn(bool) + 'static) -> Self {
+        self.config.callbacks.on_visibility_changed = Some(Box::new(callback));
+        self
+    }
+
+    /// Called with the names of all known outputs once output changes have stopped arriving for
+    /// 100ms, so a burst of mode, scale and enter/leave events triggers a single reconfiguration.
+    /// Outputs plugged in or removed while running are included.
+    #[must_use]
+    pub fn on_outputs_settled(mut self, callback: impl Fn(&[String]) + 'static) -> Self {
+        self.config.callbacks.on_outputs_settled = Some(Box::new(callback));
+        self
+    }
+
+    /// Called with `false` when the surface's output goes inactive, e.g. a monitor powered off
+    /// by DPMS, and with `true` when it comes back. Wayland has no power event, so this follows
+    /// the surface leaving and entering its output and frame callbacks stalling or resuming.
+    /// Registering it also suspends rendering while the output is inactive.
+    #[must_use]
+    pub fn on_output_enabled(mut self, callback: impl Fn(bool) + 'static) -> Self {
+        self.config.callbacks.on_output_enabled = Some(Box::new(callback));
+        self
+    }
+
+    /// Called when the surface loses keyboard focus, e.g. because the user clicked elsewhere.
+    /// Requires a keyboard interactivity other than `None` for the surface to get focus at all.
+    #[must_use]
+    pub fn on_focus_lost(mut self, callback: impl Fn() + 'static) -> Self {
+        self.config.callbacks.on_focus_lost = Some(Box::new(callback));
+        self
+    }
+
+    /// Sees every pointer and touch event before it reaches the component and decides whether
+    /// it is delivered, e.g. to implement edge-swipe gestures or shortcuts on top of it.
+    /// Surface behaviour such as revealing a hot edge happens regardless of the decision.
+    #[must_use]
+    pub fn with_input_filter(
+        mut self,
+        filter: impl FnMut(&InputEvent) -> FilterAction + 'static,
+    ) -> Self {
+        self.config.callbacks.input_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Called with a description of the offending object, e.g. `zwlr_layer_surface_v1@12: error 0:
+    /// invalid size`, when the compositor reports a protocol error. The connection is unusable
+    /// afterwards, so `run` returns the error right after; recovering means building a new
+    /// `WindowingSystem`.
+    #[must_use]
+    pub fn on_protocol_error(mut self, callback: impl Fn(&str) + 'static) -> Self {
+        self.config.callbacks.on_protocol_error = Some(Box::new(callback));
+        self
+    }
+
+    /// Called when the compositor closes the surface, e.g. because its output was unplugged, with
+    /// the names of the other outputs currently plugged in. Returning one of them re-creates the
+    /// surface there, with the same layer state and component; returning `None` closes it and
+    /// makes `run` return.
+    #[must_use]
+    pub fn on_closed(mut self, callback: impl Fn(&[String]) -> Option<String> + 'static) -> Self {
+        self.config.callbacks.on_closed = Some(Box::new(callback));
+        self
+    }
+
+    /// Called once when the compositor closes the surface and it is not moved to another output
+    /// by `on_closed`, e.g. to save state or shut down other runtimes. The event loop stops
+    /// right after the handler returns, and `run` returns `Ok(())`.
+    #[must_use]
+    pub fn with_close_handler(mut self, handler: impl FnMut() + 'static) -> Self {
+        self.config.callbacks.close_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Runs `callback` on the event loop every `interval` once `run` starts, redrawing afterwards.
+    /// The timer keeps going while the callback returns `true` and stops once it returns `false`.
+    #[must_use]
+    pub fn with_interval(
+        mut self,
+        interval: Duration,
+        callback: impl FnMut(&ComponentInstance) -> bool + 'static,
+    ) -> Self {
+        self.config.intervals.push((interval, Box::new(callback)));
+        self
+    }
+
+    /// Called with `true` when the compositor actually gives the surface keyboard focus and with
+    /// `false` when it takes it away. Requesting keyboard interactivity doesn't guarantee focus,
+    /// e.g. while another exclusive surface holds it.
+    #[must_use]
+    pub fn on_keyboard_focus_changed(mut self, callback: impl Fn(bool) + 'static) -> Self {
+        self.config.callbacks.on_keyboard_focus_changed = Some(Box::new(callback));
+        self
+    }
+
+    /// Called when the compositor releases a buffer passed to `WindowState::attach_buffer`,
+    /// i.e. when it is safe to attach the next image without stalling on in-flight buffers.
+    #[must_use]
+    pub fn on_buffer_released(mut self, callback: impl Fn() + 'static) -> Self {
+        self.config.callbacks.on_buffer_released = Some(Box::new(callback));
         self
     }
 
     #[allow(clippy::missing_errors_doc)]
     pub fn build(&mut self) -> Result<WindowingSystem, LayerShikaError> {
-        match self.config.component_definition {
-            Some(_) => WindowingSystem::new(&mut self.config),
-            None => Err(LayerShikaError::InvalidInput(
-                "Slint component not set".into(),
-            )),
+        self.compile_component()?;
+        // Checked here as well so a missing component fails before connecting to Wayland.
+        if self.config.component_definition.is_none() {
+            return Err(WindowingSystem::missing_component_error());
+        }
+        WindowingSystem::new(&mut self.config)
+    }
+
+    /// Builds the surface without connecting to Wayland, drawn into memory by Slint's software
+    /// renderer, so components and surface behavior can be exercised in tests without a
+    /// compositor or GPU. The compositor has none of the optional protocols; all outputs,
+    /// hot reload and the EGL settings don't apply.
+    #[cfg(feature = "headless")]
+    #[allow(clippy::missing_errors_doc)]
+    pub fn headless(&mut self) -> Result<HeadlessWindowingSystem, LayerShikaError> {
+        self.headless_with_protocols(OptionalProtocols {
+            fractional_scale: false,
+            cursor_shape: false,
+            viewporter: false,
+        })
+    }
+
+    /// Like [`Self::headless`], on a compositor supporting `protocols`.
+    #[cfg(feature = "headless")]
+    #[allow(clippy::missing_errors_doc)]
+    pub fn headless_with_protocols(
+        &mut self,
+        protocols: OptionalProtocols,
+    ) -> Result<HeadlessWindowingSystem, LayerShikaError> {
+        self.compile_component()?;
+        HeadlessWindowingSystem::new(&mut self.config, protocols)
+    }
+
+    fn compile_component(&mut self) -> Result<(), LayerShikaError> {
+        if let Some(language) = &self.config.language {
+            language::select_language(language);
+        }
+        if let Some(source) = &self.config.component_source {
+            self.config.component_definition = Some(source.compile(
+                self.config.component_name.as_deref(),
+                &self.config.include_paths,
+            )?);
         }
+        Ok(())
     }
 }
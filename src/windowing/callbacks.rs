@@ -0,0 +1,139 @@
+use slint::{LogicalSize, PhysicalSize};
+use slint_interpreter::ComponentInstance;
+use xkbcommon::xkb::Keysym;
+
+use super::{
+    input::{FilterAction, InputEvent},
+    keyboard::Modifiers,
+};
+
+type ResizedCallback = Box<dyn Fn(PhysicalSize, LogicalSize)>;
+type ScaleMismatchCallback = Box<dyn Fn(f32, f32)>;
+type ScaleConflictCallback = Box<dyn Fn(f32, i32)>;
+type KeyCallback = Box<dyn Fn(Keysym, Modifiers, bool)>;
+type VisibilityChangedCallback = Box<dyn Fn(bool)>;
+type OutputEnabledCallback = Box<dyn Fn(bool)>;
+type FocusLostCallback = Box<dyn Fn()>;
+type KeyboardFocusChangedCallback = Box<dyn Fn(bool)>;
+type BufferReleasedCallback = Box<dyn Fn()>;
+type InputFilter = Box<dyn FnMut(&InputEvent) -> FilterAction>;
+type ClosedCallback = Box<dyn Fn(&[String]) -> Option<String>>;
+type CloseHandler = Box<dyn FnMut()>;
+type OutputsSettledCallback = Box<dyn Fn(&[String])>;
+type ProtocolErrorCallback = Box<dyn Fn(&str)>;
+type ComponentReloadedCallback = Box<dyn Fn(&ComponentInstance)>;
+
+/// User callbacks registered through the builder and invoked from the windowing state.
+#[allow(clippy::struct_field_names)]
+#[derive(Default)]
+pub struct WindowCallbacks {
+    pub on_resized: Option<ResizedCallback>,
+    pub on_scale_mismatch: Option<ScaleMismatchCallback>,
+    pub on_scale_conflict: Option<ScaleConflictCallback>,
+    pub on_key: Option<KeyCallback>,
+    pub on_visibility_changed: Option<VisibilityChangedCallback>,
+    pub on_output_enabled: Option<OutputEnabledCallback>,
+    pub on_outputs_settled: Option<OutputsSettledCallback>,
+    pub on_focus_lost: Option<FocusLostCallback>,
+    pub on_keyboard_focus_changed: Option<KeyboardFocusChangedCallback>,
+    pub on_buffer_released: Option<BufferReleasedCallback>,
+    pub on_closed: Option<ClosedCallback>,
+    pub close_handler: Option<CloseHandler>,
+    pub input_filter: Option<InputFilter>,
+    pub on_protocol_error: Option<ProtocolErrorCallback>,
+    pub on_component_reloaded: Option<ComponentReloadedCallback>,
+}
+
+impl WindowCallbacks {
+    pub fn resized(&self, physical: PhysicalSize, logical: LogicalSize) {
+        if let Some(callback) = &self.on_resized {
+            callback(physical, logical);
+        }
+    }
+
+    pub fn scale_mismatch(&self, configured: f32, output: f32) {
+        if let Some(callback) = &self.on_scale_mismatch {
+            callback(configured, output);
+        }
+    }
+
+    pub fn scale_conflict(&self, render_scale: f32, output_scale: i32) {
+        if let Some(callback) = &self.on_scale_conflict {
+            callback(render_scale, output_scale);
+        }
+    }
+
+    pub fn key(&self, keysym: Keysym, modifiers: Modifiers, pressed: bool) {
+        if let Some(callback) = &self.on_key {
+            callback(keysym, modifiers, pressed);
+        }
+    }
+
+    pub fn visibility_changed(&self, visible: bool) {
+        if let Some(callback) = &self.on_visibility_changed {
+            callback(visible);
+        }
+    }
+
+    pub fn output_enabled(&self, enabled: bool) {
+        if let Some(callback) = &self.on_output_enabled {
+            callback(enabled);
+        }
+    }
+
+    pub fn outputs_settled(&self, outputs: &[String]) {
+        if let Some(callback) = &self.on_outputs_settled {
+            callback(outputs);
+        }
+    }
+
+    pub fn focus_lost(&self) {
+        if let Some(callback) = &self.on_focus_lost {
+            callback();
+        }
+    }
+
+    pub fn keyboard_focus_changed(&self, focused: bool) {
+        if let Some(callback) = &self.on_keyboard_focus_changed {
+            callback(focused);
+        }
+    }
+
+    pub fn buffer_released(&self) {
+        if let Some(callback) = &self.on_buffer_released {
+            callback();
+        }
+    }
+
+    pub fn filter_input(&mut self, event: &InputEvent) -> FilterAction {
+        self.input_filter
+            .as_mut()
+            .map_or(FilterAction::Forward, |filter| filter(event))
+    }
+
+    /// The output to move to after the surface was closed, if the callback picks one.
+    pub fn closed(&self, available_outputs: &[String]) -> Option<String> {
+        self.on_closed
+            .as_ref()
+            .and_then(|callback| callback(available_outputs))
+    }
+
+    /// Runs the close handler, at most once.
+    pub fn surface_closed(&mut self) {
+        if let Some(mut handler) = self.close_handler.take() {
+            handler();
+        }
+    }
+
+    pub fn protocol_error(&self, description: &str) {
+        if let Some(callback) = &self.on_protocol_error {
+            callback(description);
+        }
+    }
+
+    pub fn component_reloaded(&self, component_instance: &ComponentInstance) {
+        if let Some(callback) = &self.on_component_reloaded {
+            callback(component_instance);
+        }
+    }
+}
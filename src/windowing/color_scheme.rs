@@ -0,0 +1,21 @@
+use i_slint_core::items::ColorScheme as SlintColorScheme;
+
+/// Light/dark preference reported to Slint styles.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    /// No preference known, styles use their default scheme.
+    #[default]
+    Unknown,
+    Dark,
+    Light,
+}
+
+impl From<ColorScheme> for SlintColorScheme {
+    fn from(color_scheme: ColorScheme) -> Self {
+        match color_scheme {
+            ColorScheme::Unknown => Self::Unknown,
+            ColorScheme::Dark => Self::Dark,
+            ColorScheme::Light => Self::Light,
+        }
+    }
+}
@@ -1,33 +1,238 @@
-use slint_interpreter::ComponentDefinition;
+use crate::rendering::egl_context::SharedGlContext;
+use glutin::config::ConfigTemplateBuilder;
+use slint::{GraphicsAPI, RenderingState};
+use slint_interpreter::{ComponentDefinition, ComponentInstance, Value};
+use std::{path::PathBuf, rc::Rc, time::Duration};
+use wayland_client::Connection;
+
+#[cfg(feature = "unstable-slint-internals")]
+use super::color_scheme::ColorScheme;
+use super::{
+    callbacks::WindowCallbacks, layouts::LayoutSet, outputs::OutputMatcher,
+    snapshot::ConfigSnapshot, source::ComponentSource,
+};
 use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::{
     zwlr_layer_shell_v1::{self},
     zwlr_layer_surface_v1::{Anchor, KeyboardInteractivity},
 };
 
+pub type EglConfigHook = Box<dyn FnOnce(ConfigTemplateBuilder) -> ConfigTemplateBuilder>;
+
+pub type IntervalCallback = Box<dyn FnMut(&ComponentInstance) -> bool>;
+
+pub type RenderingNotifier = Box<dyn FnMut(RenderingState, &GraphicsAPI<'_>)>;
+
+pub enum FontSource {
+    Path(PathBuf),
+    Memory(&'static [u8]),
+}
+
+/// What the configure handler does with the exclusive zone, anchor and margin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfigureBehavior {
+    /// Re-send them along with the new size on every configure.
+    #[default]
+    Reapply,
+    /// Only send the size, leaving whatever was last set at runtime to the compositor.
+    KeepLayerState,
+}
+
+/// What happens when the compositor closes the surface and `on_closed` doesn't move it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputLossBehavior {
+    /// Stop the event loop, making `run` return.
+    #[default]
+    Close,
+    /// Destroy the surface and create it again once an output with the same name is plugged in.
+    WaitForReturn,
+}
+
+/// Where the surface height comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeightMode {
+    /// The height set with `with_height`, or at runtime.
+    #[default]
+    Fixed,
+    /// The preferred height of the component's layout, followed as it changes.
+    Auto,
+}
+
+#[allow(clippy::struct_excessive_bools)]
 pub struct WindowConfig {
     pub height: u32,
+    pub height_mode: HeightMode,
+    pub width: Option<u32>,
     pub layer: zwlr_layer_shell_v1::Layer,
     pub margin: (i32, i32, i32, i32),
     pub anchor: Anchor,
+    pub output_matcher: Option<OutputMatcher>,
+    pub all_outputs: bool,
     pub keyboard_interactivity: KeyboardInteractivity,
+    pub pointer_enabled: bool,
+    pub touch_enabled: bool,
     pub exclusive_zone: i32,
+    pub max_exclusive_fraction: Option<f32>,
+    pub configure_behavior: ConfigureBehavior,
+    pub output_loss_behavior: OutputLossBehavior,
     pub scale_factor: f32,
+    pub render_scale: f32,
+    #[cfg(feature = "unstable-slint-internals")]
+    pub color_scheme: ColorScheme,
+    #[cfg(feature = "unstable-slint-internals")]
+    pub follow_portal_color_scheme: bool,
+    pub language: Option<String>,
     pub namespace: String,
+    pub namespace_suffix: Option<String>,
+    pub hot_edge: Option<u32>,
+    pub opaque: bool,
+    pub auto_close: Option<Duration>,
+    pub z_hint: i32,
+    pub wayland_socket: Option<PathBuf>,
+    pub connection: Option<Rc<Connection>>,
+    pub egl_config: Option<EglConfigHook>,
+    pub explicit_sync: bool,
+    pub defer_renderer: bool,
+    pub follow_output_scale: bool,
+    pub configure_diagnostics: bool,
+    pub shared_gl_context: Option<SharedGlContext>,
+    pub rendering_notifier: Option<RenderingNotifier>,
     pub component_definition: Option<ComponentDefinition>,
+    pub component_source: Option<ComponentSource>,
+    pub component_name: Option<String>,
+    pub include_paths: Vec<PathBuf>,
+    #[cfg(feature = "hot-reload")]
+    pub hot_reload: bool,
+    pub properties: Vec<(String, Value)>,
+    pub initial_focus: Option<String>,
+    pub fonts: Vec<FontSource>,
+    pub intervals: Vec<(Duration, IntervalCallback)>,
+    pub layouts: LayoutSet,
+    pub callbacks: WindowCallbacks,
+}
+
+impl WindowConfig {
+    /// Whether the surface is anchored to all four edges and therefore sized by the output.
+    pub const fn is_fullscreen(&self) -> bool {
+        self.anchor.is_all()
+    }
+
+    /// Width to request from the compositor: the configured one, unless the surface is
+    /// stretched between the left and right edges.
+    pub fn requested_width(&self) -> Option<u32> {
+        self.width
+            .filter(|_| !self.anchor.contains(Anchor::Left | Anchor::Right))
+    }
+
+    /// The namespace sent to the compositor: the base namespace joined with the suffix, if any.
+    pub fn full_namespace(&self) -> String {
+        self.namespace_suffix.as_ref().map_or_else(
+            || self.namespace.clone(),
+            |suffix| format!("{}-{suffix}", self.namespace),
+        )
+    }
+
+    /// Overrides the layer surface settings with `snapshot`.
+    pub fn apply_snapshot(&mut self, snapshot: &ConfigSnapshot) {
+        self.layer = snapshot.layer;
+        self.anchor = snapshot.anchor;
+        self.margin = snapshot.margin;
+        self.height = snapshot.height;
+        self.exclusive_zone = snapshot.exclusive_zone;
+        self.keyboard_interactivity = snapshot.keyboard_interactivity;
+        self.scale_factor = snapshot.scale_factor;
+        self.namespace.clone_from(&snapshot.namespace);
+        self.namespace_suffix = None;
+    }
+
+    /// The settings a surface is created with, for the surfaces `on_all_outputs` adds when an
+    /// output is plugged in. What is handed over once at startup, like the callbacks, fonts,
+    /// intervals and the component itself, is left out.
+    pub fn surface_template(&self) -> Self {
+        Self {
+            height: self.height,
+            height_mode: self.height_mode,
+            width: self.width,
+            layer: self.layer,
+            margin: self.margin,
+            anchor: self.anchor,
+            all_outputs: self.all_outputs,
+            keyboard_interactivity: self.keyboard_interactivity,
+            pointer_enabled: self.pointer_enabled,
+            touch_enabled: self.touch_enabled,
+            exclusive_zone: self.exclusive_zone,
+            max_exclusive_fraction: self.max_exclusive_fraction,
+            configure_behavior: self.configure_behavior,
+            output_loss_behavior: self.output_loss_behavior,
+            scale_factor: self.scale_factor,
+            render_scale: self.render_scale,
+            #[cfg(feature = "unstable-slint-internals")]
+            color_scheme: self.color_scheme,
+            namespace: self.namespace.clone(),
+            namespace_suffix: self.namespace_suffix.clone(),
+            hot_edge: self.hot_edge,
+            opaque: self.opaque,
+            auto_close: self.auto_close,
+            defer_renderer: self.defer_renderer,
+            follow_output_scale: self.follow_output_scale,
+            configure_diagnostics: self.configure_diagnostics,
+            properties: self.properties.clone(),
+            ..Self::default()
+        }
+    }
 }
 
 impl Default for WindowConfig {
     fn default() -> Self {
         Self {
             height: 30,
+            height_mode: HeightMode::default(),
+            width: None,
             layer: zwlr_layer_shell_v1::Layer::Top,
             margin: (0, 0, 0, 0),
             anchor: Anchor::Top | Anchor::Left | Anchor::Right,
+            output_matcher: None,
+            all_outputs: false,
             keyboard_interactivity: KeyboardInteractivity::OnDemand,
+            pointer_enabled: true,
+            touch_enabled: true,
             exclusive_zone: -1,
+            max_exclusive_fraction: None,
+            configure_behavior: ConfigureBehavior::default(),
+            output_loss_behavior: OutputLossBehavior::default(),
             namespace: "layer-shika".to_owned(),
+            namespace_suffix: None,
             scale_factor: 1.0,
+            render_scale: 1.0,
+            #[cfg(feature = "unstable-slint-internals")]
+            color_scheme: ColorScheme::Unknown,
+            #[cfg(feature = "unstable-slint-internals")]
+            follow_portal_color_scheme: false,
+            language: None,
+            hot_edge: None,
+            opaque: false,
+            auto_close: None,
+            z_hint: 0,
+            wayland_socket: None,
+            connection: None,
+            egl_config: None,
+            explicit_sync: false,
+            defer_renderer: false,
+            follow_output_scale: true,
+            configure_diagnostics: false,
+            shared_gl_context: None,
+            rendering_notifier: None,
             component_definition: None,
+            component_source: None,
+            component_name: None,
+            include_paths: Vec::new(),
+            #[cfg(feature = "hot-reload")]
+            hot_reload: false,
+            properties: Vec::new(),
+            initial_focus: None,
+            fonts: Vec::new(),
+            intervals: Vec::new(),
+            layouts: LayoutSet::new(),
+            callbacks: WindowCallbacks::default(),
         }
     }
 }
@@ -0,0 +1,377 @@
+use log::info;
+use slint::{
+    platform::software_renderer::PremultipliedRgbaColor, LogicalSize, PhysicalSize,
+    SharedPixelBuffer,
+};
+use slint_interpreter::{ComponentInstance, Value};
+use smithay_client_toolkit::reexports::{
+    calloop::EventLoop,
+    protocols::wp::fractional_scale::v1::client::wp_fractional_scale_v1::{
+        self, WpFractionalScaleV1,
+    },
+    protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::{
+        self, Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1,
+    },
+};
+use std::{io, os::unix::net::UnixStream, time::Duration};
+use wayland_client::{
+    protocol::{
+        wl_output::{self, WlOutput},
+        wl_pointer::{self, WlPointer},
+        wl_registry::WlRegistry,
+    },
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+
+use crate::{errors::LayerShikaError, impl_empty_dispatch};
+
+use super::{
+    config::WindowConfig, layer_surface::LayerSurfaceRequest, mirror::SharedGlobals,
+    outputs::TrackedOutput, state::WindowState, Backend, Globals, OptionalProtocols,
+    SurfaceContent, WindowingSystem,
+};
+
+/// Mode of the output the surface starts on, as a compositor would announce it after binding.
+const HEADLESS_OUTPUT_SIZE: (i32, i32) = (1920, 1080);
+
+/// Evdev code of the left pointer button, from `linux/input-event-codes.h`.
+const BTN_LEFT: u32 = 0x110;
+
+// The registry the globals are bound from never reports any.
+impl_empty_dispatch!((WlRegistry, ()));
+
+/// A surface driven through the same state as on a compositor, but without one.
+///
+/// Layer-shell requests are recorded instead of sent, frames are drawn by Slint's software
+/// renderer into memory, and compositor events are simulated by calling the methods below. Meant
+/// for testing components and surface behavior without a compositor or GPU.
+pub struct HeadlessWindowingSystem {
+    state: WindowState,
+    connection: Connection,
+    queue_handle: QueueHandle<WindowState>,
+    event_loop: EventLoop<'static, WindowState>,
+    /// The compositor's end of the connection. Nothing answers on it; requests sent to it are
+    /// read and dropped so they never fill up the socket.
+    compositor: UnixStream,
+    output: WlOutput,
+    /// Object simulated configures arrive on and are acked through; the surface itself only
+    /// records its layer-shell requests.
+    layer_surface: ZwlrLayerSurfaceV1,
+    configure_serial: u32,
+}
+
+impl HeadlessWindowingSystem {
+    /// Sets up the surface as on a compositor supporting `protocols`, and announces a
+    /// 1920x1080 output at scale 1. The surface waits for [`Self::configure`] like a real one.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn new(
+        config: &mut WindowConfig,
+        protocols: OptionalProtocols,
+    ) -> Result<Self, LayerShikaError> {
+        info!("Initializing headless WindowingSystem");
+        let (socket, compositor) = UnixStream::pair()
+            .and_then(|pair| pair.1.set_nonblocking(true).map(|()| pair))
+            .map_err(|e| LayerShikaError::WaylandSocket(e.to_string()))?;
+        let connection =
+            Connection::from_socket(socket).map_err(LayerShikaError::WaylandConnection)?;
+        let queue_handle = connection.new_event_queue().handle();
+        let globals = Self::bind_globals(&connection, &queue_handle, protocols);
+        WindowingSystem::validate_config(config, globals.viewporter.as_ref(), &[])?;
+        let output = globals.output.clone();
+        let layer_shell = globals.layer_shell.clone();
+
+        let component_definition = config
+            .component_definition
+            .take()
+            .ok_or_else(WindowingSystem::missing_component_error)?;
+
+        let context_builder = WindowingSystem::egl_context_builder(&connection.display(), config);
+        let event_loop =
+            EventLoop::try_new().map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+        let content = SurfaceContent {
+            component_definition,
+            properties: config.properties.clone(),
+            initial_focus: config.initial_focus.take(),
+            callbacks: std::mem::take(&mut config.callbacks),
+            shared_globals: SharedGlobals::Own,
+        };
+        let state = WindowingSystem::create_state(
+            config,
+            globals,
+            &queue_handle,
+            &event_loop,
+            &context_builder,
+            content,
+            Backend::Headless,
+        )?;
+        let layer_surface = layer_shell.get_layer_surface(
+            &state.surface(),
+            Some(&output),
+            config.layer,
+            config.full_namespace(),
+            &queue_handle,
+            (),
+        );
+
+        let mut headless = Self {
+            state,
+            connection,
+            queue_handle,
+            event_loop,
+            compositor,
+            output,
+            layer_surface,
+            configure_serial: 0,
+        };
+        let (width, height) = HEADLESS_OUTPUT_SIZE;
+        headless.output_mode(width, height);
+        headless.output_scale(1);
+        headless.output_done();
+        Ok(headless)
+    }
+
+    /// Binds the globals on a registry nobody answers, with the versions `WindowingSystem`
+    /// binds them with at most.
+    fn bind_globals(
+        connection: &Connection,
+        queue_handle: &QueueHandle<WindowState>,
+        protocols: OptionalProtocols,
+    ) -> Globals {
+        let registry = connection.display().get_registry(queue_handle, ());
+        let output: WlOutput = registry.bind(4, 4, queue_handle, ());
+        Globals {
+            compositor: registry.bind(1, 4, queue_handle, ()),
+            layer_shell: registry.bind(2, 1, queue_handle, ()),
+            seat: registry.bind(3, 8, queue_handle, ()),
+            outputs: vec![TrackedOutput::new(4, output.clone())],
+            output,
+            shm: registry.bind(5, 1, queue_handle, ()),
+            viewporter: protocols
+                .viewporter
+                .then(|| registry.bind(6, 1, queue_handle, ())),
+            fractional_scale_manager: protocols
+                .fractional_scale
+                .then(|| registry.bind(7, 1, queue_handle, ())),
+            advertised: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn component_instance(&self) -> &ComponentInstance {
+        self.state.component_instance()
+    }
+
+    /// The surface size from the last configure or resize, in surface coordinates.
+    #[must_use]
+    pub const fn size(&self) -> PhysicalSize {
+        *self.state.size()
+    }
+
+    /// Size of the buffer the component is rendered at, i.e. the surface size times the output
+    /// scale being followed and the render scale.
+    #[must_use]
+    pub fn buffer_size(&self) -> PhysicalSize {
+        slint::platform::WindowAdapter::size(&*self.state.window())
+    }
+
+    /// Size the component's layout prefers, updated by Slint whenever it changes.
+    #[must_use]
+    pub fn preferred_size(&self) -> LogicalSize {
+        self.state.window().preferred_size()
+    }
+
+    /// Hands out the layer-shell requests made since the last call, oldest first.
+    pub fn take_requests(&mut self) -> Vec<LayerSurfaceRequest> {
+        self.state.layer_surface().take_requests()
+    }
+
+    /// Simulates `zwlr_layer_surface_v1.configure`; zero leaves that dimension to the surface.
+    pub fn configure(&mut self, width: u32, height: u32) {
+        self.configure_serial += 1;
+        let layer_surface = self.layer_surface.clone();
+        self.dispatch(
+            &layer_surface,
+            zwlr_layer_surface_v1::Event::Configure {
+                serial: self.configure_serial,
+                width,
+                height,
+            },
+        );
+    }
+
+    /// Simulates `wl_output.mode` for the surface's output.
+    pub fn output_mode(&mut self, width: i32, height: i32) {
+        let output = self.output.clone();
+        self.dispatch(
+            &output,
+            wl_output::Event::Mode {
+                flags: WEnum::Value(wl_output::Mode::Current),
+                width,
+                height,
+                refresh: 60_000,
+            },
+        );
+    }
+
+    /// Simulates `wl_output.scale` for the surface's output, applied on [`Self::output_done`].
+    pub fn output_scale(&mut self, factor: i32) {
+        let output = self.output.clone();
+        self.dispatch(&output, wl_output::Event::Scale { factor });
+    }
+
+    /// Simulates `wl_output.done`, completing a batch of output changes.
+    pub fn output_done(&mut self) {
+        let output = self.output.clone();
+        self.dispatch(&output, wl_output::Event::Done);
+    }
+
+    /// Simulates `wp_fractional_scale_v1.preferred_scale`, in 120ths. Only has an effect when
+    /// following the output scale on a compositor with fractional scaling.
+    pub fn preferred_scale(&mut self, scale: u32) {
+        let fractional_scale = self.inert::<WpFractionalScaleV1>();
+        self.dispatch(
+            &fractional_scale,
+            wp_fractional_scale_v1::Event::PreferredScale { scale },
+        );
+    }
+
+    /// Simulates the pointer entering the surface at `x`, `y` in surface coordinates.
+    pub fn pointer_enter(&mut self, x: f64, y: f64) {
+        let surface = (*self.state.surface()).clone();
+        self.dispatch_pointer(wl_pointer::Event::Enter {
+            serial: 0,
+            surface,
+            surface_x: x,
+            surface_y: y,
+        });
+    }
+
+    pub fn pointer_motion(&mut self, x: f64, y: f64) {
+        self.dispatch_pointer(wl_pointer::Event::Motion {
+            time: 0,
+            surface_x: x,
+            surface_y: y,
+        });
+    }
+
+    /// Simulates pressing or releasing the left pointer button.
+    pub fn pointer_button(&mut self, pressed: bool) {
+        let state = if pressed {
+            wl_pointer::ButtonState::Pressed
+        } else {
+            wl_pointer::ButtonState::Released
+        };
+        self.dispatch_pointer(wl_pointer::Event::Button {
+            serial: 0,
+            time: 0,
+            button: BTN_LEFT,
+            state: WEnum::Value(state),
+        });
+    }
+
+    pub fn pointer_leave(&mut self) {
+        let surface = (*self.state.surface()).clone();
+        self.dispatch_pointer(wl_pointer::Event::Leave { serial: 0, surface });
+    }
+
+    /// See [`WindowingSystem::set_size`].
+    #[allow(clippy::missing_errors_doc)]
+    pub fn set_size(&mut self, width: u32, height: u32) -> Result<(), LayerShikaError> {
+        WindowingSystem::validate_size(width, height)?;
+        self.state.set_size(width, height);
+        self.flush();
+        Ok(())
+    }
+
+    /// See [`WindowState::set_anchor`].
+    pub fn set_anchor(&mut self, anchor: Anchor) {
+        self.state.set_anchor(anchor);
+        self.flush();
+    }
+
+    /// See [`WindowState::set_keyboard_interactivity`].
+    pub fn set_keyboard_interactivity(&mut self, keyboard_interactivity: KeyboardInteractivity) {
+        self.state
+            .set_keyboard_interactivity(keyboard_interactivity);
+        self.flush();
+    }
+
+    /// See [`WindowState::blank`].
+    pub fn blank(&mut self) {
+        self.state.blank();
+        self.flush();
+    }
+
+    /// See [`WindowState::restore`].
+    pub fn restore(&mut self) {
+        self.state.restore();
+        self.flush();
+    }
+
+    /// See [`WindowState::reapply_config`].
+    pub fn reapply_config(&mut self) {
+        self.state.reapply_config();
+        self.flush();
+    }
+
+    /// See [`WindowState::set_property`].
+    #[allow(clippy::missing_errors_doc)]
+    pub fn set_property(&self, name: &str, value: Value) -> Result<(), LayerShikaError> {
+        self.state.set_property(name, value)
+    }
+
+    /// See [`WindowState::get_property`].
+    #[allow(clippy::missing_errors_doc)]
+    pub fn get_property(&self, name: &str) -> Result<Value, LayerShikaError> {
+        self.state.get_property(name)
+    }
+
+    /// Runs due timers and animations, then draws a frame if the component changed, as one
+    /// iteration of `run` would, and returns the last frame drawn.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn render(&mut self) -> Result<SharedPixelBuffer<PremultipliedRgbaColor>, LayerShikaError> {
+        slint::platform::update_timers_and_animations();
+        self.event_loop
+            .dispatch(Duration::ZERO, &mut self.state)
+            .map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+        self.state.render_frame_if_dirty()?;
+        self.flush();
+        self.state
+            .window()
+            .frame()
+            .ok_or_else(|| LayerShikaError::Rendering("The surface has no software frame".into()))
+    }
+
+    fn dispatch_pointer(&mut self, event: wl_pointer::Event) {
+        let pointer = self.inert::<WlPointer>();
+        self.dispatch(&pointer, event);
+    }
+
+    /// Delivers `event` to the state as if it had been read from the compositor.
+    fn dispatch<I: Proxy>(&mut self, proxy: &I, event: I::Event)
+    where
+        WindowState: Dispatch<I, ()>,
+    {
+        <WindowState as Dispatch<I, ()>>::event(
+            &mut self.state,
+            proxy,
+            event,
+            &(),
+            &self.connection,
+            &self.queue_handle,
+        );
+        self.flush();
+    }
+
+    /// An object without id, for events whose handling neither depends on the object they arrive
+    /// on nor sends requests on it.
+    fn inert<I: Proxy>(&self) -> I {
+        I::inert(self.connection.backend().downgrade())
+    }
+
+    fn flush(&self) {
+        // Both fail once everything sent is gone, which is all that's wanted here.
+        self.connection.flush().ok();
+        io::copy(&mut &self.compositor, &mut io::sink()).ok();
+    }
+}
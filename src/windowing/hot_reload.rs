@@ -0,0 +1,150 @@
+use log::{error, info, warn};
+use notify::{
+    event::{AccessKind, AccessMode, ModifyKind},
+    Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use smithay_client_toolkit::reexports::calloop::{
+    channel::{self, Sender},
+    timer::{TimeoutAction, Timer},
+    LoopHandle,
+};
+use std::{
+    cell::Cell,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+use crate::errors::LayerShikaError;
+
+use super::{config::WindowConfig, language, source::ComponentSource, state::WindowState};
+
+/// Editors save in several writes, so a reload waits this long after the first change for the
+/// rest of the save.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Watches the directory of the component's markup and swaps a freshly compiled component into
+/// every surface when a `.slint` file in it changes, see `with_hot_reload`.
+pub struct HotReload {
+    _watcher: RecommendedWatcher,
+}
+
+/// What the component is compiled from again on every change.
+struct Recompile {
+    source: ComponentSource,
+    name: Option<String>,
+    include_paths: Vec<PathBuf>,
+}
+
+impl HotReload {
+    /// Starts watching and reloads the component of the state driven by each of `loop_handles`.
+    pub fn watch(
+        config: &WindowConfig,
+        loop_handles: &[LoopHandle<'static, WindowState>],
+    ) -> Result<Self, LayerShikaError> {
+        let Some(ComponentSource::Path(path)) = &config.component_source else {
+            return Err(LayerShikaError::WindowConfiguration(
+                "hot reload needs the component to be loaded with with_component_path".into(),
+            ));
+        };
+        // Watching the directory rather than the file survives editors replacing it on save.
+        let directory = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let recompile = Rc::new(Recompile {
+            source: ComponentSource::Path(path.clone()),
+            name: config.component_name.clone(),
+            include_paths: config.include_paths.clone(),
+        });
+
+        let senders = loop_handles
+            .iter()
+            .map(|loop_handle| Self::listen(loop_handle, &recompile))
+            .collect::<Result<Vec<_>, _>>()?;
+        language::note_thread_started();
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            match result {
+                Ok(event) if Self::changes_markup(&event) => {
+                    for sender in &senders {
+                        // Fails only once the surface's loop is gone, e.g. its output was removed.
+                        sender.send(()).ok();
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Error watching Slint markup: {e}"),
+            }
+        })
+        .map_err(|e| LayerShikaError::InvalidInput(format!("Failed to set up file watch: {e}")))?;
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .map_err(|e| {
+                LayerShikaError::InvalidInput(format!(
+                    "Failed to watch {}: {e}",
+                    directory.display()
+                ))
+            })?;
+        info!("Watching {} for Slint markup changes", directory.display());
+
+        Ok(Self { _watcher: watcher })
+    }
+
+    /// Receives change notifications on `loop_handle` and reloads shortly after the first one.
+    fn listen(
+        loop_handle: &LoopHandle<'static, WindowState>,
+        recompile: &Rc<Recompile>,
+    ) -> Result<Sender<()>, LayerShikaError> {
+        let (sender, receiver) = channel::channel();
+        let timer_handle = loop_handle.clone();
+        let recompile = Rc::clone(recompile);
+        let pending = Rc::new(Cell::new(false));
+        loop_handle
+            .insert_source(receiver, move |event, (), _| {
+                if !matches!(event, channel::Event::Msg(())) || pending.replace(true) {
+                    return;
+                }
+                let recompile = Rc::clone(&recompile);
+                let timer_pending = Rc::clone(&pending);
+                let timer = Timer::from_duration(RELOAD_DEBOUNCE);
+                let inserted = timer_handle.insert_source(timer, move |_, (), shared_data| {
+                    timer_pending.set(false);
+                    recompile.reload(shared_data);
+                    TimeoutAction::Drop
+                });
+                if let Err(e) = inserted {
+                    pending.set(false);
+                    error!("Failed to schedule component reload: {e}");
+                }
+            })
+            .map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+        Ok(sender)
+    }
+
+    fn changes_markup(event: &Event) -> bool {
+        let written = matches!(
+            event.kind,
+            EventKind::Create(_)
+                | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any)
+                | EventKind::Access(AccessKind::Close(AccessMode::Write))
+        );
+        written
+            && event.paths.iter().any(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "slint")
+            })
+    }
+}
+
+impl Recompile {
+    /// Keeps the current component if the markup doesn't compile, e.g. while it is being edited.
+    fn reload(&self, state: &mut WindowState) {
+        info!("Slint markup changed, reloading component");
+        let result = self
+            .source
+            .compile(self.name.as_deref(), &self.include_paths)
+            .and_then(|definition| state.replace_component(definition));
+        if let Err(e) = result {
+            error!("Failed to reload component: {e}");
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use slint::platform::WindowEvent;
+
+/// The kind of device an [`InputEvent`] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputSource {
+    Pointer,
+    /// The first touch point, which Slint sees as a left-button pointer.
+    Touch,
+}
+
+/// A pointer or touch event about to be delivered to Slint, see
+/// [`crate::LayerShika::with_input_filter`].
+#[derive(Debug, Clone)]
+pub struct InputEvent {
+    pub source: InputSource,
+    /// The event as Slint would receive it, with the position in logical coordinates.
+    pub event: WindowEvent,
+}
+
+/// What an input filter decides to do with an [`InputEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterAction {
+    /// Deliver the event to the component.
+    #[default]
+    Forward,
+    /// Swallow the event; the component never sees it.
+    Consume,
+}
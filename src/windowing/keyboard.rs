@@ -0,0 +1,190 @@
+use crate::errors::LayerShikaError;
+use slint::{platform::Key, SharedString};
+use std::{os::fd::OwnedFd, time::Duration};
+use xkbcommon::xkb::{self, Keycode, Keysym};
+
+/// Evdev keycodes are offset by 8 from the xkb keycodes they map to.
+const EVDEV_OFFSET: u32 = 8;
+
+/// Repeat settings used until the compositor sends `wl_keyboard.repeat_info`, which it only
+/// does from `wl_seat` v4 on.
+const DEFAULT_REPEAT_RATE: i32 = 25;
+const DEFAULT_REPEAT_DELAY: Duration = Duration::from_millis(600);
+
+/// State of the modifier keys at the time of a key event.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub caps_lock: bool,
+    pub logo: bool,
+    pub num_lock: bool,
+}
+
+pub struct KeyboardState {
+    context: xkb::Context,
+    state: Option<xkb::State>,
+    modifiers: Modifiers,
+    focused: bool,
+    /// Key repeats per second, `0` if repeat is disabled.
+    repeat_rate: i32,
+    repeat_delay: Duration,
+}
+
+impl KeyboardState {
+    pub fn new() -> Self {
+        Self {
+            context: xkb::Context::new(xkb::CONTEXT_NO_FLAGS),
+            state: None,
+            modifiers: Modifiers::default(),
+            focused: false,
+            repeat_rate: DEFAULT_REPEAT_RATE,
+            repeat_delay: DEFAULT_REPEAT_DELAY,
+        }
+    }
+
+    /// Compiles the keymap shared by the compositor through `fd`, replacing the previous one.
+    /// The fd is mapped private and read-only, as `wl_keyboard` v7 requires, and unmapped and
+    /// closed once the keymap is compiled.
+    pub fn load_keymap(&mut self, fd: OwnedFd, size: u32) -> Result<(), LayerShikaError> {
+        if size == 0 {
+            return Err(LayerShikaError::InvalidInput("Empty keymap".into()));
+        }
+        let size = usize::try_from(size)
+            .map_err(|e| LayerShikaError::InvalidInput(format!("Invalid keymap size: {e}")))?;
+        let keymap = unsafe {
+            xkb::Keymap::new_from_fd(
+                &self.context,
+                fd,
+                size,
+                xkb::KEYMAP_FORMAT_TEXT_V1,
+                xkb::KEYMAP_COMPILE_NO_FLAGS,
+            )
+        }
+        .map_err(|e| LayerShikaError::InvalidInput(format!("Failed to map keymap: {e}")))?
+        .ok_or_else(|| LayerShikaError::InvalidInput("Failed to compile keymap".into()))?;
+
+        self.state = Some(xkb::State::new(&keymap));
+        self.modifiers = Modifiers::default();
+        Ok(())
+    }
+
+    pub fn update_modifiers(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+        state.update_mask(depressed, latched, locked, 0, 0, group);
+
+        let is_active = |name| state.mod_name_is_active(name, xkb::STATE_MODS_EFFECTIVE);
+        self.modifiers = Modifiers {
+            ctrl: is_active(xkb::MOD_NAME_CTRL),
+            alt: is_active(xkb::MOD_NAME_ALT),
+            shift: is_active(xkb::MOD_NAME_SHIFT),
+            caps_lock: is_active(xkb::MOD_NAME_CAPS),
+            logo: is_active(xkb::MOD_NAME_LOGO),
+            num_lock: is_active(xkb::MOD_NAME_NUM),
+        };
+    }
+
+    /// Resolves an evdev keycode from `wl_keyboard::Event::Key` to its keysym, or `None` until
+    /// a keymap has been received.
+    pub fn keysym(&self, key: u32) -> Option<Keysym> {
+        self.state
+            .as_ref()
+            .map(|state| state.key_get_one_sym(Keycode::new(key + EVDEV_OFFSET)))
+    }
+
+    /// Text Slint expects in `KeyPressed`/`KeyReleased` for the evdev keycode `key`: the
+    /// character the key produces at the current shift level, or the code of a special key such
+    /// as `Key::Backspace`. Control is not applied, so Ctrl+A yields `a` like Slint's shortcuts
+    /// expect. `None` for keys Slint has no representation of.
+    pub fn key_text(&self, key: u32) -> Option<SharedString> {
+        let keysym = self.keysym(key)?;
+        if let Some(special) = special_key(keysym) {
+            return Some(special.into());
+        }
+        char::from_u32(xkb::keysym_to_utf32(keysym))
+            .filter(|c| !c.is_control())
+            .map(|c| c.to_string().into())
+    }
+
+    pub fn set_repeat_info(&mut self, rate: i32, delay: i32) {
+        self.repeat_rate = rate.max(0);
+        self.repeat_delay = Duration::from_millis(u64::try_from(delay).unwrap_or_default());
+    }
+
+    /// Delay before a held `key` starts repeating and the interval between repeats, or `None`
+    /// if repeat is disabled or the keymap says the key doesn't repeat, like modifiers.
+    pub fn repeat_timing(&self, key: u32) -> Option<(Duration, Duration)> {
+        let rate = u32::try_from(self.repeat_rate)
+            .ok()
+            .filter(|&rate| rate > 0)?;
+        let state = self.state.as_ref()?;
+        if !state
+            .get_keymap()
+            .key_repeats(Keycode::new(key + EVDEV_OFFSET))
+        {
+            return None;
+        }
+        Some((self.repeat_delay, Duration::from_secs(1) / rate))
+    }
+
+    pub const fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    pub const fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    pub const fn has_focus(&self) -> bool {
+        self.focused
+    }
+}
+
+/// Maps keysyms without a printable character to the Slint key with the same meaning.
+const fn special_key(keysym: Keysym) -> Option<Key> {
+    let key = match keysym {
+        Keysym::BackSpace => Key::Backspace,
+        Keysym::Tab => Key::Tab,
+        Keysym::ISO_Left_Tab => Key::Backtab,
+        Keysym::Return | Keysym::KP_Enter => Key::Return,
+        Keysym::Escape => Key::Escape,
+        Keysym::Delete | Keysym::KP_Delete => Key::Delete,
+        Keysym::Shift_L => Key::Shift,
+        Keysym::Shift_R => Key::ShiftR,
+        Keysym::Control_L => Key::Control,
+        Keysym::Control_R => Key::ControlR,
+        Keysym::Alt_L | Keysym::Alt_R => Key::Alt,
+        Keysym::ISO_Level3_Shift | Keysym::Mode_switch => Key::AltGr,
+        Keysym::Caps_Lock => Key::CapsLock,
+        Keysym::Super_L | Keysym::Meta_L => Key::Meta,
+        Keysym::Super_R | Keysym::Meta_R => Key::MetaR,
+        Keysym::Up | Keysym::KP_Up => Key::UpArrow,
+        Keysym::Down | Keysym::KP_Down => Key::DownArrow,
+        Keysym::Left | Keysym::KP_Left => Key::LeftArrow,
+        Keysym::Right | Keysym::KP_Right => Key::RightArrow,
+        Keysym::Home | Keysym::KP_Home => Key::Home,
+        Keysym::End | Keysym::KP_End => Key::End,
+        Keysym::Page_Up | Keysym::KP_Page_Up => Key::PageUp,
+        Keysym::Page_Down | Keysym::KP_Page_Down => Key::PageDown,
+        Keysym::Insert | Keysym::KP_Insert => Key::Insert,
+        Keysym::Menu => Key::Menu,
+        Keysym::F1 => Key::F1,
+        Keysym::F2 => Key::F2,
+        Keysym::F3 => Key::F3,
+        Keysym::F4 => Key::F4,
+        Keysym::F5 => Key::F5,
+        Keysym::F6 => Key::F6,
+        Keysym::F7 => Key::F7,
+        Keysym::F8 => Key::F8,
+        Keysym::F9 => Key::F9,
+        Keysym::F10 => Key::F10,
+        Keysym::F11 => Key::F11,
+        Keysym::F12 => Key::F12,
+        _ => return None,
+    };
+    Some(key)
+}
@@ -0,0 +1,56 @@
+use log::{info, warn};
+use std::{
+    env,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+
+/// The language written to `LANGUAGE`, which happens at most once per process.
+static SELECTED: OnceLock<String> = OnceLock::new();
+/// Set before this crate starts a thread, after which the environment is left alone.
+static THREADS_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Records that this crate is about to start a thread that may read the environment, e.g. the
+/// portal listener or the hot-reload watcher.
+pub fn note_thread_started() {
+    THREADS_STARTED.store(true, Ordering::SeqCst);
+}
+
+/// Selects the catalog `@tr` strings are translated with.
+///
+/// Slint 1.7 translates through gettext, which is only compiled in when the application enables
+/// Slint's `gettext` feature and binds its text domain (`slint::init_translations!`). gettext
+/// gives the `LANGUAGE` environment variable precedence over `LANG`, so the language is selected
+/// by setting it. Writing the environment is only sound while no other thread reads it, so it is
+/// written once, and not at all once this crate started a thread. Without the `gettext` feature
+/// `@tr` strings are shown untranslated and this has no visible effect.
+pub fn select_language(language: &str) {
+    if THREADS_STARTED.load(Ordering::SeqCst) {
+        warn!("Not selecting language {language}: layer-shika already started threads");
+        return;
+    }
+    if SELECTED.set(language.to_owned()).is_err() {
+        if SELECTED.get().is_some_and(|selected| selected != language) {
+            warn!("Not selecting language {language}: the language was already selected");
+        }
+        return;
+    }
+    info!("Selecting language {}", language);
+    env::set_var("LANGUAGE", language);
+}
+
+/// Re-translates strings that are already translated, by invalidating gettext's cache and
+/// Slint's translated strings, which is only possible through Slint's internals.
+///
+/// The environment is not written at runtime, and Slint 1.7 has no API to select bundled
+/// translations, so a language other than the one selected at startup only shows up once the
+/// application switched gettext's locale itself.
+#[cfg(feature = "unstable-slint-internals")]
+pub fn switch_language(language: &str) {
+    if SELECTED.get().is_none_or(|selected| selected != language) {
+        info!("Re-translating for language {language}, as selected by the application");
+    }
+    i_slint_core::translations::mark_all_translations_dirty();
+}
@@ -0,0 +1,93 @@
+use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::{
+    Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1,
+};
+#[cfg(feature = "headless")]
+use std::cell::RefCell;
+
+/// A request on the `zwlr_layer_surface_v1` of a surface, as recorded by the headless backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerSurfaceRequest {
+    SetSize(u32, u32),
+    SetAnchor(Anchor),
+    SetExclusiveZone(i32),
+    /// Top, right, bottom and left margin.
+    SetMargin(i32, i32, i32, i32),
+    SetKeyboardInteractivity(KeyboardInteractivity),
+    Destroy,
+}
+
+/// Where the layer-shell requests of a surface go: the compositor, or a log of them when the
+/// surface is driven without one.
+pub enum LayerSurface {
+    Wayland(ZwlrLayerSurfaceV1),
+    #[cfg(feature = "headless")]
+    Recording(RefCell<Vec<LayerSurfaceRequest>>),
+}
+
+impl LayerSurface {
+    #[cfg(feature = "headless")]
+    pub const fn recording() -> Self {
+        Self::Recording(RefCell::new(Vec::new()))
+    }
+
+    pub fn set_size(&self, width: u32, height: u32) {
+        self.send(LayerSurfaceRequest::SetSize(width, height));
+    }
+
+    pub fn set_anchor(&self, anchor: Anchor) {
+        self.send(LayerSurfaceRequest::SetAnchor(anchor));
+    }
+
+    pub fn set_exclusive_zone(&self, exclusive_zone: i32) {
+        self.send(LayerSurfaceRequest::SetExclusiveZone(exclusive_zone));
+    }
+
+    pub fn set_margin(&self, top: i32, right: i32, bottom: i32, left: i32) {
+        self.send(LayerSurfaceRequest::SetMargin(top, right, bottom, left));
+    }
+
+    pub fn set_keyboard_interactivity(&self, keyboard_interactivity: KeyboardInteractivity) {
+        self.send(LayerSurfaceRequest::SetKeyboardInteractivity(
+            keyboard_interactivity,
+        ));
+    }
+
+    pub fn destroy(&self) {
+        self.send(LayerSurfaceRequest::Destroy);
+    }
+
+    /// Hands out the requests recorded since the last call, oldest first. Always empty for a
+    /// surface on a compositor.
+    #[cfg(feature = "headless")]
+    pub fn take_requests(&self) -> Vec<LayerSurfaceRequest> {
+        match self {
+            Self::Wayland(_) => Vec::new(),
+            Self::Recording(requests) => requests.take(),
+        }
+    }
+
+    fn send(&self, request: LayerSurfaceRequest) {
+        let layer_surface = match self {
+            Self::Wayland(layer_surface) => layer_surface,
+            #[cfg(feature = "headless")]
+            Self::Recording(requests) => {
+                requests.borrow_mut().push(request);
+                return;
+            }
+        };
+        match request {
+            LayerSurfaceRequest::SetSize(width, height) => layer_surface.set_size(width, height),
+            LayerSurfaceRequest::SetAnchor(anchor) => layer_surface.set_anchor(anchor),
+            LayerSurfaceRequest::SetExclusiveZone(exclusive_zone) => {
+                layer_surface.set_exclusive_zone(exclusive_zone);
+            }
+            LayerSurfaceRequest::SetMargin(top, right, bottom, left) => {
+                layer_surface.set_margin(top, right, bottom, left);
+            }
+            LayerSurfaceRequest::SetKeyboardInteractivity(keyboard_interactivity) => {
+                layer_surface.set_keyboard_interactivity(keyboard_interactivity);
+            }
+            LayerSurfaceRequest::Destroy => layer_surface.destroy(),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use super::snapshot::ConfigSnapshot;
+
+/// Named layout presets, e.g. "compact" and "expanded", that can be switched between at runtime
+/// with `WindowingSystem::apply_layout`.
+#[derive(Debug, Clone, Default)]
+pub struct LayoutSet {
+    layouts: Vec<(String, ConfigSnapshot)>,
+}
+
+impl LayoutSet {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_layout(mut self, name: &str, layout: ConfigSnapshot) -> Self {
+        self.insert(name, layout);
+        self
+    }
+
+    /// Adds a preset, replacing any previous one of the same name.
+    pub fn insert(&mut self, name: &str, layout: ConfigSnapshot) {
+        match self.layouts.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => *existing = layout,
+            None => self.layouts.push((name.to_owned(), layout)),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<ConfigSnapshot> {
+        let index = self.layouts.iter().position(|(n, _)| n == name)?;
+        Some(self.layouts.remove(index).1)
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ConfigSnapshot> {
+        self.layouts
+            .iter()
+            .find_map(|(n, layout)| (n == name).then_some(layout))
+    }
+
+    /// Preset names in insertion order, e.g. to cycle through them.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.layouts.iter().map(|(name, _)| name.as_str())
+    }
+}
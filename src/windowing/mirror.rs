@@ -0,0 +1,131 @@
+use std::{os::fd::AsFd, time::Duration};
+
+use smithay_client_toolkit::reexports::calloop::{
+    generic::Generic, EventLoop, Interest, LoopHandle, Mode, PostAction, RegistrationToken,
+};
+use wayland_client::{
+    protocol::{
+        wl_keyboard,
+        wl_output::{self, WlOutput},
+        wl_pointer::{self, WlPointer},
+        wl_seat::Capability,
+        wl_touch,
+    },
+    EventQueue,
+};
+
+use crate::errors::LayerShikaError;
+
+use super::state::{dispatches, WindowState};
+
+/// An event of the outputs or the seat's input devices. Mirrors share them with the first
+/// surface, which binds them on the main queue and so is the only one receiving their events;
+/// it queues them up to be handed on to the mirrors.
+pub enum SharedEvent {
+    OutputAdded(u32, WlOutput),
+    OutputRemoved(u32),
+    Output(WlOutput, wl_output::Event),
+    /// Input devices released because the seat lost them.
+    DevicesRemoved(Capability),
+    Pointer(WlPointer, wl_pointer::Event),
+    Keyboard(wl_keyboard::Event),
+    Touch(wl_touch::Event),
+}
+
+/// Where a surface's outputs and input devices come from.
+#[derive(Default)]
+pub enum SharedGlobals {
+    /// Bound for this surface alone.
+    #[default]
+    Own,
+    /// Bound for this surface and shared with mirrors, with their events queued for them.
+    Relayed(Vec<SharedEvent>),
+    /// Bound by the first surface, which hands their events on.
+    Borrowed,
+}
+
+/// The surface repeated on another output by [`crate::LayerShika::on_all_outputs`]. It has its
+/// own queue and event loop, so the events of its surface objects and its timers reach its own
+/// state; both are driven from the main loop. The globals, outputs and input devices are the
+/// first surface's, see [`SharedEvent`].
+pub struct OutputMirror {
+    pub state: WindowState,
+    event_queue: EventQueue<WindowState>,
+    event_loop: EventLoop<'static, WindowState>,
+    wakeup: Option<(LoopHandle<'static, WindowState>, RegistrationToken)>,
+}
+
+impl OutputMirror {
+    pub const fn new(
+        state: WindowState,
+        event_queue: EventQueue<WindowState>,
+        event_loop: EventLoop<'static, WindowState>,
+    ) -> Self {
+        Self {
+            state,
+            event_queue,
+            event_loop,
+            wakeup: None,
+        }
+    }
+
+    /// Wakes `main_loop` whenever one of this mirror's timers is due.
+    pub fn wake(
+        &mut self,
+        main_loop: &LoopHandle<'static, WindowState>,
+    ) -> Result<(), LayerShikaError> {
+        if self.wakeup.is_some() {
+            return Ok(());
+        }
+        let poller = self
+            .event_loop
+            .as_fd()
+            .try_clone_to_owned()
+            .map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+        let token = main_loop
+            .insert_source(
+                Generic::new(poller, Interest::READ, Mode::Level),
+                |_, _poller, _shared_data| Ok(PostAction::Continue),
+            )
+            .map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+        self.wakeup = Some((main_loop.clone(), token));
+        Ok(())
+    }
+
+    pub fn event_loop_handle(&self) -> LoopHandle<'static, WindowState> {
+        self.event_loop.handle()
+    }
+
+    /// Hands on an event of the outputs or input devices shared with the first surface.
+    pub fn relay(&mut self, event: &SharedEvent) {
+        dispatches::handle_shared_event(&mut self.state, event);
+    }
+
+    /// Blocks until the compositor has processed every request sent so far, dispatching this
+    /// surface's events that arrive meanwhile.
+    pub fn roundtrip(&mut self) -> Result<(), LayerShikaError> {
+        self.event_queue
+            .roundtrip(&mut self.state)
+            .map_err(|e| LayerShikaError::WaylandProtocol(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Dispatches the events the main loop read for this surface and its due timers, then draws.
+    pub fn dispatch(&mut self) -> Result<(), LayerShikaError> {
+        self.event_queue
+            .dispatch_pending(&mut self.state)
+            .map_err(|e| LayerShikaError::WaylandProtocol(e.to_string()))?;
+        self.event_loop
+            .dispatch(Duration::ZERO, &mut self.state)
+            .map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+        self.state.render_frame_if_dirty()
+    }
+}
+
+impl Drop for OutputMirror {
+    fn drop(&mut self) {
+        if let Some((main_loop, token)) = self.wakeup.take() {
+            main_loop.remove(token);
+        }
+    }
+}
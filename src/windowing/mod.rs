@@ -2,108 +2,619 @@ use self::state::WindowState;
 use crate::{
     bind_globals,
     errors::LayerShikaError,
-    rendering::{egl_context::EGLContext, femtovg_window::FemtoVGWindow},
+    rendering::{
+        egl_context::{EGLContext, EGLContextBuilder, SharedGlContext},
+        femtovg_window::FemtoVGWindow,
+    },
 };
-use config::WindowConfig;
-use log::{debug, error, info};
-use slint::{platform::femtovg_renderer::FemtoVGRenderer, LogicalPosition, PhysicalSize};
-use slint_interpreter::ComponentInstance;
+use callbacks::WindowCallbacks;
+use config::{FontSource, IntervalCallback, WindowConfig};
+use glutin::config::ConfigTemplateBuilder;
+#[cfg(feature = "hot-reload")]
+use hot_reload::HotReload;
+use layer_surface::LayerSurface;
+use layouts::LayoutSet;
+use log::{debug, error, info, warn};
+use mirror::{OutputMirror, SharedEvent, SharedGlobals};
+use outputs::TrackedOutput;
+use slint::{LogicalPosition, PhysicalSize};
+use slint_interpreter::{ComponentDefinition, ComponentInstance, Value};
 use smithay_client_toolkit::reexports::{
-    calloop::{self, EventLoop, Interest, LoopHandle, Mode, PostAction},
+    calloop::{
+        self,
+        timer::{TimeoutAction, Timer},
+        EventLoop, Interest, LoopHandle, Mode, PostAction, RegistrationToken,
+    },
+    protocols::wp::{
+        fractional_scale::v1::client::{
+            wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+            wp_fractional_scale_v1::WpFractionalScaleV1,
+        },
+        viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter},
+    },
     protocols_wlr::layer_shell::v1::client::{
-        zwlr_layer_shell_v1::ZwlrLayerShellV1, zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+        zwlr_layer_shell_v1::ZwlrLayerShellV1,
+        zwlr_layer_surface_v1::{Anchor, KeyboardInteractivity},
     },
 };
+use snapshot::ConfigSnapshot;
+use stacking::StackingSlot;
 use state::builder::WindowStateBuilder;
-use std::rc::Rc;
+use std::{
+    cell::RefCell, env, io, iter, os::unix::net::UnixStream, path::PathBuf, rc::Rc, time::Duration,
+};
 use wayland_client::{
-    globals::registry_queue_init,
+    backend::WaylandError,
+    globals::{registry_queue_init, GlobalList},
     protocol::{
-        wl_compositor::WlCompositor, wl_display::WlDisplay, wl_output::WlOutput, wl_seat::WlSeat,
+        wl_compositor::WlCompositor,
+        wl_display::WlDisplay,
+        wl_output::{self, WlOutput},
+        wl_seat::WlSeat,
+        wl_shm::WlShm,
         wl_surface::WlSurface,
     },
     Connection, EventQueue, Proxy, QueueHandle,
 };
 
 pub mod builder;
+mod callbacks;
+#[cfg(feature = "unstable-slint-internals")]
+pub mod color_scheme;
 mod config;
+#[cfg(feature = "headless")]
+pub mod headless;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+pub mod input;
+pub mod keyboard;
+mod language;
+pub mod layer_surface;
+pub mod layouts;
 mod macros;
+mod mirror;
+pub mod outputs;
+#[cfg(feature = "unstable-slint-internals")]
+mod portal;
+mod seat;
+mod shm;
+pub mod snapshot;
+mod source;
+mod stacking;
 mod state;
+mod visibility;
+
+const VISIBILITY_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone)]
+struct Globals {
+    compositor: WlCompositor,
+    output: WlOutput,
+    outputs: Vec<TrackedOutput>,
+    layer_shell: ZwlrLayerShellV1,
+    seat: WlSeat,
+    shm: WlShm,
+    viewporter: Option<WpViewporter>,
+    fractional_scale_manager: Option<WpFractionalScaleManagerV1>,
+    advertised: Vec<(String, u32)>,
+}
+
+/// What a surface shows, whom it reports to and whether it shares its outputs and input
+/// devices, see [`WindowingSystem::create_state`].
+struct SurfaceContent {
+    component_definition: ComponentDefinition,
+    properties: Vec<(String, Value)>,
+    initial_focus: Option<String>,
+    callbacks: WindowCallbacks,
+    shared_globals: SharedGlobals,
+}
+
+/// Creates the surface for an output plugged in after startup with
+/// [`crate::LayerShika::on_all_outputs`], unless a surface waiting for it already came back.
+struct MirrorFactory {
+    config: WindowConfig,
+    globals: Globals,
+    context_builder: EGLContextBuilder,
+    main_loop: LoopHandle<'static, WindowState>,
+    intervals: Vec<(Duration, Rc<RefCell<IntervalCallback>>)>,
+    /// Outputs plugged in after startup that haven't sent their first `done` yet.
+    new_outputs: Vec<(u32, WlOutput)>,
+}
+
+impl MirrorFactory {
+    /// Follows the outputs coming and going, creating a surface once a new one is described.
+    fn handle(
+        &mut self,
+        event: &SharedEvent,
+        connection: &Connection,
+        state: &WindowState,
+        mirrors: &mut Vec<OutputMirror>,
+    ) {
+        match event {
+            SharedEvent::OutputAdded(global_name, output) => {
+                self.new_outputs.push((*global_name, output.clone()));
+            }
+            SharedEvent::OutputRemoved(global_name) => {
+                self.new_outputs.retain(|(name, _)| name != global_name);
+            }
+            SharedEvent::Output(output, wl_output::Event::Done) => {
+                let Some(index) = self.new_outputs.iter().position(|(_, new)| new == output) else {
+                    return;
+                };
+                self.new_outputs.remove(index);
+                if state.shows_output(output)
+                    || mirrors
+                        .iter()
+                        .any(|mirror| mirror.state.shows_output(output))
+                {
+                    return;
+                }
+                match self.create(connection, state, output) {
+                    Ok(mirror) => mirrors.push(mirror),
+                    Err(e) => error!("Failed to create the surface on a new output: {e}"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Repeats the first surface on `output`, as it is now: with its layer settings, component
+    /// and the property values changed at runtime.
+    fn create(
+        &self,
+        connection: &Connection,
+        state: &WindowState,
+        output: &WlOutput,
+    ) -> Result<OutputMirror, LayerShikaError> {
+        let mut config = self.config.surface_template();
+        config.apply_snapshot(&state.config_snapshot());
+        let globals = Globals {
+            output: output.clone(),
+            outputs: state.outputs().to_vec(),
+            ..self.globals.clone()
+        };
+        let mut mirror = WindowingSystem::create_mirror(
+            &mut config,
+            connection,
+            globals,
+            &self.context_builder,
+            state.component_definition(),
+        )?;
+        mirror.state.adopt_output_info();
+        mirror
+            .state
+            .set_static_rendering(state.is_static_rendering());
+        for (name, value) in state.changed_properties() {
+            if let Err(e) = mirror.state.set_property(&name, value) {
+                debug!("Not carrying over property {name}: {e}");
+            }
+        }
+        mirror.wake(&self.main_loop)?;
+        let loop_handle = mirror.event_loop_handle();
+        for (interval, callback) in &self.intervals {
+            WindowingSystem::insert_interval(&loop_handle, *interval, Rc::clone(callback))?;
+        }
+        Ok(mirror)
+    }
+}
+
+/// Where a surface's frames and layer-shell requests go.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    /// `FemtoVG` on an EGL context, and the compositor's layer shell.
+    Wayland,
+    /// Slint's software renderer and a recording layer surface, see `HeadlessWindowingSystem`.
+    #[cfg(feature = "headless")]
+    Headless,
+}
+
+/// Optional protocols whose presence changes what the crate can do on a compositor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptionalProtocols {
+    pub fractional_scale: bool,
+    pub cursor_shape: bool,
+    pub viewporter: bool,
+}
 
 pub struct WindowingSystem {
     state: WindowState,
+    mirrors: Vec<OutputMirror>,
+    mirror_factory: Option<MirrorFactory>,
     connection: Rc<Connection>,
     event_queue: EventQueue<WindowState>,
     event_loop: EventLoop<'static, WindowState>,
+    intervals: Vec<(Duration, IntervalCallback)>,
+    layouts: LayoutSet,
+    advertised_globals: Vec<(String, u32)>,
+    stacking: StackingSlot,
+    #[cfg(feature = "hot-reload")]
+    _hot_reload: Option<HotReload>,
 }
 
 impl WindowingSystem {
+    #[allow(clippy::too_many_lines)]
     fn new(config: &mut WindowConfig) -> Result<Self, LayerShikaError> {
         info!("Initializing WindowingSystem");
-        let connection =
-            Rc::new(Connection::connect_to_env().map_err(LayerShikaError::WaylandConnection)?);
-        let event_queue = connection.new_event_queue();
+        let connection = match config.connection.take() {
+            Some(connection) => {
+                info!("Reusing the embedder's Wayland connection");
+                connection
+            }
+            None => Rc::new(Self::connect(config)?),
+        };
+        // The registry reports to this queue, which becomes the main queue so that outputs
+        // plugged in or removed later are seen.
+        let (global_list, event_queue) = registry_queue_init::<WindowState>(&connection)
+            .map_err(|e| LayerShikaError::GlobalInitialization(e.to_string()))?;
+        let first_output = config
+            .output_matcher
+            .as_ref()
+            .map(|matcher| outputs::find_output(&connection, &global_list, matcher))
+            .transpose()?
+            .unwrap_or(0);
+        let globals = Self::initialize_globals(&global_list, &event_queue.handle(), first_output)
+            .map_err(|e| LayerShikaError::GlobalInitialization(e.to_string()))?;
+        Self::validate_config(config, globals.viewporter.as_ref(), &globals.advertised)?;
+        let advertised = globals.advertised.clone();
 
-        let (compositor, output, layer_shell, seat) =
-            Self::initialize_globals(&connection, &event_queue.handle())
-                .map_err(|e| LayerShikaError::GlobalInitialization(e.to_string()))?;
+        let stacking = StackingSlot::register(config.layer, config.z_hint);
+        let component_definition = config
+            .component_definition
+            .take()
+            .ok_or_else(Self::missing_component_error)?;
 
+        let context_builder = Self::egl_context_builder(&connection.display(), config);
+        let event_loop =
+            EventLoop::try_new().map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+        // With `all_outputs`, the globals are bound once and shared with the other surfaces.
+        let shared = config.all_outputs.then(|| globals.clone());
+        let content = SurfaceContent {
+            component_definition: component_definition.clone(),
+            properties: config.properties.clone(),
+            initial_focus: config.initial_focus.take(),
+            callbacks: std::mem::take(&mut config.callbacks),
+            shared_globals: if shared.is_some() {
+                SharedGlobals::Relayed(Vec::new())
+            } else {
+                SharedGlobals::Own
+            },
+        };
+        let state = Self::create_state(
+            config,
+            globals,
+            &event_queue.handle(),
+            &event_loop,
+            &context_builder,
+            content,
+            Backend::Wayland,
+        )?;
+
+        let mirrors = match &shared {
+            Some(shared) => shared
+                .outputs
+                .iter()
+                .enumerate()
+                .filter(|&(index, _)| index != first_output)
+                .map(|(_, output)| {
+                    let globals = Globals {
+                        output: output.output.clone(),
+                        ..shared.clone()
+                    };
+                    Self::create_mirror(
+                        config,
+                        &connection,
+                        globals,
+                        &context_builder,
+                        &component_definition,
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+        let mirror_factory = shared.map(|globals| MirrorFactory {
+            config: config.surface_template(),
+            globals,
+            context_builder: context_builder.clone(),
+            main_loop: event_loop.handle(),
+            intervals: Vec::new(),
+            new_outputs: Vec::new(),
+        });
+
+        #[cfg(any(feature = "unstable-slint-internals", feature = "hot-reload"))]
+        let loop_handles: Vec<_> = iter::once(event_loop.handle())
+            .chain(mirrors.iter().map(OutputMirror::event_loop_handle))
+            .collect();
+        #[cfg(feature = "unstable-slint-internals")]
+        if config.follow_portal_color_scheme {
+            portal::follow_color_scheme(&loop_handles)?;
+        }
+        #[cfg(feature = "hot-reload")]
+        let hot_reload = if config.hot_reload {
+            Some(HotReload::watch(config, &loop_handles)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            state,
+            mirrors,
+            mirror_factory,
+            connection,
+            event_queue,
+            event_loop,
+            intervals: std::mem::take(&mut config.intervals),
+            layouts: std::mem::take(&mut config.layouts),
+            advertised_globals: advertised,
+            stacking,
+            #[cfg(feature = "hot-reload")]
+            _hot_reload: hot_reload,
+        })
+    }
+
+    /// Creates the layer surface on `globals.output` and the state driving it, with its objects
+    /// bound on `queue_handle` and its timers on `event_loop`.
+    fn create_state(
+        config: &mut WindowConfig,
+        globals: Globals,
+        queue_handle: &QueueHandle<WindowState>,
+        event_loop: &EventLoop<'static, WindowState>,
+        context_builder: &EGLContextBuilder,
+        content: SurfaceContent,
+        backend: Backend,
+    ) -> Result<WindowState, LayerShikaError> {
+        let Globals {
+            compositor,
+            output,
+            outputs,
+            layer_shell,
+            seat,
+            shm,
+            viewporter,
+            fractional_scale_manager,
+            ..
+        } = globals;
         let (surface, layer_surface) = Self::setup_surface(
             &compositor,
             &output,
             &layer_shell,
-            &event_queue.handle(),
+            queue_handle,
             config,
+            backend,
         );
 
-        let pointer = Rc::new(seat.get_pointer(&event_queue.handle(), ()));
-        let window = Self::initialize_renderer(&surface, &connection.display(), config)
-            .map_err(|e| LayerShikaError::EGLContextCreation(e.to_string()))?;
-        let component_definition = config.component_definition.take().ok_or_else(|| {
-            LayerShikaError::WindowConfiguration("Component definition is required".to_string())
-        })?;
+        let (viewport, fractional_scale) = Self::setup_scaling(
+            viewporter.as_ref(),
+            fractional_scale_manager,
+            &surface,
+            queue_handle,
+            config,
+        );
+        let context_builder = context_builder.clone().with_surface_id(surface.id());
+        let (window, pending_context) =
+            Self::initialize_renderer(context_builder.clone(), config, backend)?;
 
-        let state = WindowStateBuilder::new()
-            .with_component_definition(component_definition)
+        WindowStateBuilder::new()
+            .with_component_definition(content.component_definition)
+            .with_properties(content.properties)
+            .with_initial_focus(content.initial_focus)
+            .with_compositor(Rc::new(compositor))
+            .with_shm(shm)
+            .with_queue_handle(queue_handle.clone())
             .with_surface(Rc::clone(&surface))
             .with_layer_surface(Rc::clone(&layer_surface))
-            .with_pointer(Rc::clone(&pointer))
+            .with_layer_shell(layer_shell)
+            .with_outputs(output, outputs)
+            // Only the first surface follows the matcher; mirrors stay on their own output.
+            .with_output_matcher(config.output_matcher.take())
+            .with_shared_globals(content.shared_globals)
+            .with_layer(config.layer)
+            .with_viewporter(viewporter)
+            .with_viewport(viewport)
+            .with_fractional_scale(fractional_scale)
+            .with_seat(seat, config.pointer_enabled, config.touch_enabled)
             .with_scale_factor(config.scale_factor)
+            .with_render_scale(config.render_scale)
+            .with_follow_output_scale(config.follow_output_scale)
+            .with_configure_diagnostics(config.configure_diagnostics)
             .with_height(config.height)
+            .with_height_mode(config.height_mode)
+            .with_width(config.width)
             .with_exclusive_zone(config.exclusive_zone)
+            .with_max_exclusive_fraction(config.max_exclusive_fraction)
+            .with_configure_behavior(config.configure_behavior)
+            .with_output_loss_behavior(config.output_loss_behavior)
+            .with_anchor(config.anchor)
+            .with_margin(config.margin)
+            .with_keyboard_interactivity(config.keyboard_interactivity)
+            .with_hot_edge(config.hot_edge)
+            .with_opaque(config.opaque)
+            .with_namespace(config.full_namespace())
+            .with_callbacks(content.callbacks)
             .with_window(window)
+            .with_context_builder(context_builder)
+            .with_pending_context(pending_context)
+            .with_event_loop(event_loop.handle(), event_loop.get_signal())
+            .with_auto_close(config.auto_close)
             .build()
-            .map_err(|e| LayerShikaError::WindowConfiguration(e.to_string()))?;
+            .map_err(|e| LayerShikaError::WindowConfiguration(e.to_string()))
+    }
 
+    /// Repeats the surface on `globals.output`, with its own queue and event loop for its surface
+    /// objects and timers. The globals are the first surface's, which hands on the events of the
+    /// outputs and input devices. Callbacks and the initial focus belong to the first surface only.
+    fn create_mirror(
+        config: &mut WindowConfig,
+        connection: &Connection,
+        globals: Globals,
+        context_builder: &EGLContextBuilder,
+        component_definition: &ComponentDefinition,
+    ) -> Result<OutputMirror, LayerShikaError> {
+        info!("Creating layer surface on another output");
+        let event_queue = connection.new_event_queue();
         let event_loop =
             EventLoop::try_new().map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+        let content = SurfaceContent {
+            component_definition: component_definition.clone(),
+            properties: config.properties.clone(),
+            initial_focus: None,
+            callbacks: WindowCallbacks::default(),
+            shared_globals: SharedGlobals::Borrowed,
+        };
+        let state = Self::create_state(
+            config,
+            globals,
+            &event_queue.handle(),
+            &event_loop,
+            context_builder,
+            content,
+            Backend::Wayland,
+        )?;
+        Ok(OutputMirror::new(state, event_queue, event_loop))
+    }
 
-        Ok(Self {
-            state,
-            connection,
-            event_queue,
-            event_loop,
-        })
+    fn missing_component_error() -> LayerShikaError {
+        LayerShikaError::WindowConfiguration("component definition is required".into())
+    }
+
+    fn connect(config: &WindowConfig) -> Result<Connection, LayerShikaError> {
+        let Some(socket) = &config.wayland_socket else {
+            return Connection::connect_to_env().map_err(LayerShikaError::WaylandConnection);
+        };
+
+        let path = if socket.is_absolute() {
+            socket.clone()
+        } else {
+            let runtime_dir = env::var_os("XDG_RUNTIME_DIR").ok_or_else(|| {
+                LayerShikaError::WaylandSocket(format!(
+                    "XDG_RUNTIME_DIR is not set, cannot resolve {}",
+                    socket.display()
+                ))
+            })?;
+            PathBuf::from(runtime_dir).join(socket)
+        };
+
+        info!("Connecting to Wayland socket {}", path.display());
+        let stream = UnixStream::connect(&path)
+            .map_err(|e| LayerShikaError::WaylandSocket(format!("{}: {e}", path.display())))?;
+        Connection::from_socket(stream).map_err(LayerShikaError::WaylandConnection)
     }
 
+    /// Binds the globals on `queue_handle`, starting the surface on the output at `first_output`.
     fn initialize_globals(
-        connection: &Connection,
+        global_list: &GlobalList,
         queue_handle: &QueueHandle<WindowState>,
-    ) -> Result<(WlCompositor, WlOutput, ZwlrLayerShellV1, WlSeat), LayerShikaError> {
-        let global_list = registry_queue_init::<WindowState>(connection)
-            .map(|(global_list, _)| global_list)
-            .map_err(|e| LayerShikaError::GlobalInitialization(e.to_string()))?;
-
-        let (compositor, output, layer_shell, seat) = bind_globals!(
-            &global_list,
+        first_output: usize,
+    ) -> Result<Globals, LayerShikaError> {
+        let (compositor, layer_shell, seat, shm) = bind_globals!(
+            global_list,
             queue_handle,
-            (WlCompositor, compositor, 1..=1),
-            (WlOutput, output, 1..=1),
+            (WlCompositor, compositor, 1..=4),
             (ZwlrLayerShellV1, layer_shell, 1..=1),
-            (WlSeat, seat, 1..=1)
+            // v8 for `wl_pointer.axis_value120` from high-resolution wheels.
+            (WlSeat, seat, 1..=8),
+            (WlShm, shm, 1..=1)
         )?;
 
-        Ok((compositor, output, layer_shell, seat))
+        let viewporter = global_list.bind(queue_handle, 1..=1, ()).ok();
+        let fractional_scale_manager = global_list.bind(queue_handle, 1..=1, ()).ok();
+
+        // Every output is bound so the surface can move to another one when its output goes
+        // away; the surface starts on the first, or the one picked by the output matcher.
+        let outputs = global_list.contents().with_list(|globals| {
+            globals
+                .iter()
+                .filter(|global| global.interface == WlOutput::interface().name)
+                .map(|global| {
+                    let output = global_list.registry().bind::<WlOutput, _, _>(
+                        global.name,
+                        global.version.min(4),
+                        queue_handle,
+                        (),
+                    );
+                    TrackedOutput::new(global.name, output)
+                })
+                .collect::<Vec<_>>()
+        });
+        let output = outputs
+            .get(first_output)
+            .map(|tracked| tracked.output.clone())
+            .ok_or_else(|| {
+                LayerShikaError::GlobalInitialization("The compositor has no outputs".into())
+            })?;
+
+        let advertised = global_list.contents().with_list(|globals| {
+            globals
+                .iter()
+                .map(|global| (global.interface.clone(), global.version))
+                .collect::<Vec<_>>()
+        });
+        for (interface, version) in &advertised {
+            debug!("Compositor advertises {interface} v{version}");
+        }
+
+        Ok(Globals {
+            compositor,
+            output,
+            outputs,
+            layer_shell,
+            seat,
+            shm,
+            viewporter,
+            fractional_scale_manager,
+            advertised,
+        })
+    }
+
+    fn validate_config(
+        config: &mut WindowConfig,
+        viewporter: Option<&WpViewporter>,
+        advertised: &[(String, u32)],
+    ) -> Result<(), LayerShikaError> {
+        Self::validate_render_scale(config, viewporter)?;
+        Self::validate_max_exclusive_fraction(config)?;
+        if config.explicit_sync {
+            Self::report_explicit_sync(advertised);
+        }
+        Ok(())
+    }
+
+    fn report_explicit_sync(advertised: &[(String, u32)]) {
+        if advertised
+            .iter()
+            .any(|(interface, _)| interface == "wp_linux_drm_syncobj_manager_v1")
+        {
+            info!("Compositor supports explicit sync, but it is not implemented yet; relying on the driver's implicit sync");
+        } else {
+            warn!("Explicit sync requested, but the compositor does not support wp_linux_drm_syncobj_v1");
+        }
+    }
+
+    fn validate_render_scale(
+        config: &mut WindowConfig,
+        viewporter: Option<&WpViewporter>,
+    ) -> Result<(), LayerShikaError> {
+        if !(config.render_scale.is_finite() && config.render_scale > 0.0) {
+            return Err(LayerShikaError::InvalidInput(format!(
+                "Render scale must be positive, got {}",
+                config.render_scale
+            )));
+        }
+        if viewporter.is_none() && (config.render_scale - 1.0).abs() > f32::EPSILON {
+            warn!("Compositor does not support wp_viewporter, rendering at full resolution");
+            config.render_scale = 1.0;
+        }
+        Ok(())
+    }
+
+    fn validate_max_exclusive_fraction(config: &WindowConfig) -> Result<(), LayerShikaError> {
+        match config.max_exclusive_fraction {
+            Some(fraction) if !(fraction > 0.0 && fraction <= 1.0) => {
+                Err(LayerShikaError::InvalidInput(format!(
+                    "Maximum exclusive zone fraction must be in (0, 1], got {fraction}"
+                )))
+            }
+            _ => Ok(()),
+        }
     }
 
     fn setup_surface(
@@ -112,16 +623,21 @@ impl WindowingSystem {
         layer_shell: &ZwlrLayerShellV1,
         queue_handle: &QueueHandle<WindowState>,
         config: &WindowConfig,
-    ) -> (Rc<WlSurface>, Rc<ZwlrLayerSurfaceV1>) {
+        backend: Backend,
+    ) -> (Rc<WlSurface>, Rc<LayerSurface>) {
         let surface = Rc::new(compositor.create_surface(queue_handle, ()));
-        let layer_surface = Rc::new(layer_shell.get_layer_surface(
-            &surface,
-            Some(output),
-            config.layer,
-            config.namespace.clone(),
-            queue_handle,
-            (),
-        ));
+        let layer_surface = Rc::new(match backend {
+            Backend::Wayland => LayerSurface::Wayland(layer_shell.get_layer_surface(
+                &surface,
+                Some(output),
+                config.layer,
+                config.full_namespace(),
+                queue_handle,
+                (),
+            )),
+            #[cfg(feature = "headless")]
+            Backend::Headless => LayerSurface::recording(),
+        });
 
         Self::configure_layer_surface(&layer_surface, &surface, config);
 
@@ -129,7 +645,7 @@ impl WindowingSystem {
     }
 
     fn configure_layer_surface(
-        layer_surface: &Rc<ZwlrLayerSurfaceV1>,
+        layer_surface: &LayerSurface,
         surface: &WlSurface,
         config: &WindowConfig,
     ) {
@@ -143,69 +659,317 @@ impl WindowingSystem {
 
         layer_surface.set_exclusive_zone(config.exclusive_zone);
         layer_surface.set_keyboard_interactivity(config.keyboard_interactivity);
-        layer_surface.set_size(1, config.height);
+        if config.is_fullscreen() {
+            // Let the compositor size the surface to the full output.
+            layer_surface.set_size(0, 0);
+        } else {
+            layer_surface.set_size(config.requested_width().unwrap_or(1), config.height);
+        }
         surface.commit();
     }
 
-    fn initialize_renderer(
-        surface: &Rc<WlSurface>,
-        display: &WlDisplay,
+    /// Creates the surface's viewport and, when following the output scale, its fractional
+    /// scale object. Fractional scales can only be shown through the viewport, which maps the
+    /// buffer back to the surface size; without either, the integer output scale is followed.
+    fn setup_scaling(
+        viewporter: Option<&WpViewporter>,
+        fractional_scale_manager: Option<WpFractionalScaleManagerV1>,
+        surface: &WlSurface,
+        queue_handle: &QueueHandle<WindowState>,
         config: &WindowConfig,
-    ) -> Result<Rc<FemtoVGWindow>, LayerShikaError> {
-        let init_size = PhysicalSize::new(1, 1);
+    ) -> (
+        Option<WpViewport>,
+        Option<(WpFractionalScaleManagerV1, WpFractionalScaleV1)>,
+    ) {
+        let viewport =
+            viewporter.map(|viewporter| viewporter.get_viewport(surface, queue_handle, ()));
+        let fractional_scale = fractional_scale_manager
+            .filter(|_| config.follow_output_scale && viewport.is_some())
+            .map(|manager| {
+                let object = manager.get_fractional_scale(surface, queue_handle, ());
+                (manager, object)
+            });
+        (viewport, fractional_scale)
+    }
 
-        let context = EGLContext::builder()
-            .with_display_id(display.id())
-            .with_surface_id(surface.id())
-            .with_size(init_size)
-            .build()
-            .map_err(|e| LayerShikaError::EGLContextCreation(e.to_string()))?;
+    /// Template for the EGL contexts of all surfaces, which set their own surface id.
+    fn egl_context_builder(display: &WlDisplay, config: &mut WindowConfig) -> EGLContextBuilder {
+        let mut builder = EGLContext::builder().with_display_id(display.id());
+        if let Some(configure) = config.egl_config.take() {
+            builder = builder.with_config_template(configure(ConfigTemplateBuilder::new()));
+        }
+        if let Some(share_context) = config.shared_gl_context.take() {
+            builder = builder.with_share_context(share_context);
+        }
+        builder
+    }
+
+    fn initialize_renderer(
+        builder: EGLContextBuilder,
+        config: &mut WindowConfig,
+        backend: Backend,
+    ) -> Result<(Rc<FemtoVGWindow>, Option<EGLContextBuilder>), LayerShikaError> {
+        let init_size = PhysicalSize::new(1, 1);
+        let builder = builder.with_size(init_size);
 
-        let renderer = FemtoVGRenderer::new(context)
-            .map_err(|e| LayerShikaError::FemtoVGRendererCreation(e.to_string()))?;
+        let (femtovg_window, pending_context) = match backend {
+            Backend::Wayland => {
+                let femtovg_window = FemtoVGWindow::new_suspended();
+                let pending_context = if config.defer_renderer {
+                    info!("Deferring EGL context creation until the first frame");
+                    Some(builder)
+                } else {
+                    let context = builder
+                        .build()
+                        .map_err(|e| LayerShikaError::EGLContextCreation(e.to_string()))?;
+                    femtovg_window.attach_context(context)?;
+                    None
+                };
+                (femtovg_window, pending_context)
+            }
+            #[cfg(feature = "headless")]
+            Backend::Headless => (FemtoVGWindow::new_software(), None),
+        };
 
-        let femtovg_window = FemtoVGWindow::new(renderer);
         femtovg_window.set_size(slint::WindowSize::Physical(init_size));
-        femtovg_window.set_scale_factor(config.scale_factor);
+        femtovg_window.set_scale_factor(config.scale_factor * config.render_scale);
+        #[cfg(feature = "unstable-slint-internals")]
+        femtovg_window.set_color_scheme(config.color_scheme);
+        if let Some(notifier) = config.rendering_notifier.take() {
+            femtovg_window
+                .set_rendering_notifier(notifier)
+                .map_err(|e| LayerShikaError::Rendering(e.to_string()))?;
+        }
         femtovg_window.set_position(LogicalPosition::new(0., 0.));
 
-        Ok(femtovg_window)
+        for font in config.fonts.drain(..) {
+            match font {
+                FontSource::Path(path) => femtovg_window.register_font_from_path(&path)?,
+                FontSource::Memory(data) => femtovg_window.register_font_from_memory(data)?,
+            }
+        }
+
+        Ok((femtovg_window, pending_context))
     }
 
     pub fn event_loop_handle(&self) -> LoopHandle<'static, WindowState> {
         self.event_loop.handle()
     }
 
+    /// Calls `callback` with the component instance every `interval`, starting one `interval`
+    /// from now, e.g. to update a clock. The returned token removes the timer again through
+    /// [`Self::event_loop_handle`].
+    pub fn add_timer(
+        &self,
+        interval: Duration,
+        mut callback: impl FnMut(&ComponentInstance) + 'static,
+    ) -> Result<RegistrationToken, LayerShikaError> {
+        self.event_loop
+            .handle()
+            .insert_source(Timer::from_duration(interval), move |_, (), shared_data| {
+                callback(shared_data.component_instance());
+                TimeoutAction::ToDuration(interval)
+            })
+            .map_err(|e| LayerShikaError::EventLoop(e.to_string()))
+    }
+
     pub fn run(&mut self) -> Result<(), LayerShikaError> {
         info!("Starting WindowingSystem main loop");
 
-        while self
-            .event_queue
-            .blocking_dispatch(&mut self.state)
-            .map_err(|e| LayerShikaError::WaylandProtocol(e.to_string()))?
-            > 0
-        {
-            self.connection
-                .flush()
-                .map_err(|e| LayerShikaError::WaylandProtocol(e.to_string()))?;
-            self.state
-                .window()
-                .render_frame_if_dirty()
-                .map_err(|e| LayerShikaError::Rendering(e.to_string()))?;
+        // Wait for the initial configure and draw the first frame before handing over to calloop,
+        // which drives everything else, including the timers registered on it.
+        if let Err(e) = self.event_queue.roundtrip(&mut self.state) {
+            return Err(self.protocol_failure(&e));
+        }
+        if self.state.is_closed() {
+            info!("Layer surface closed before the main loop started");
+            return Ok(());
+        }
+        self.state
+            .render_frame_if_dirty()
+            .map_err(|e| LayerShikaError::Rendering(e.to_string()))?;
+        if let Err(e) = self.connection.flush() {
+            return Err(self.protocol_failure(&e));
         }
 
         self.setup_wayland_event_source()?;
+        self.setup_mirrors()?;
+        if self.state.tracks_visibility() {
+            self.setup_visibility_timer()?;
+        }
+        self.setup_intervals()?;
 
         let event_queue = &mut self.event_queue;
         let connection = &self.connection;
+        let mirrors = &mut self.mirrors;
+        let mirror_factory = &mut self.mirror_factory;
+        let stacking = &self.stacking;
 
         self.event_loop
             .run(None, &mut self.state, move |shared_data| {
                 if let Err(e) = Self::process_events(connection, event_queue, shared_data) {
-                    error!("Error processing events: {}", e);
+                    if let Some(description) = Self::describe_protocol_error(connection) {
+                        shared_data.handle_protocol_error(&description);
+                    } else {
+                        error!("Error processing events: {}", e);
+                    }
+                }
+                if stacking.take_restack() {
+                    shared_data.restack();
                 }
+                Self::dispatch_mirrors(connection, shared_data, mirrors, mirror_factory.as_mut());
             })
-            .map_err(|e| LayerShikaError::EventLoop(e.to_string()))
+            .map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+
+        Self::describe_protocol_error(&self.connection).map_or(Ok(()), |description| {
+            Err(LayerShikaError::WaylandProtocol(description))
+        })
+    }
+
+    /// The protocol error the compositor killed the connection with, naming the offending
+    /// object, or `None` if the connection is still usable.
+    fn describe_protocol_error(connection: &Connection) -> Option<String> {
+        connection.protocol_error().map(|e| {
+            format!(
+                "{}@{}: error {}: {}",
+                e.object_interface, e.object_id, e.code, e.message
+            )
+        })
+    }
+
+    /// Turns a failed request or dispatch outside the event loop into an error, reporting it to
+    /// `on_protocol_error` first if the compositor raised a protocol error.
+    fn protocol_failure(&self, error: &impl std::fmt::Display) -> LayerShikaError {
+        let description = Self::describe_protocol_error(&self.connection).map_or_else(
+            || error.to_string(),
+            |description| {
+                self.state.handle_protocol_error(&description);
+                description
+            },
+        );
+        LayerShikaError::WaylandProtocol(description)
+    }
+
+    /// Runs the event loop for content that never animates: input is still dispatched, but frames
+    /// are only drawn on configure or when requested with [`Self::render_once`].
+    pub fn run_static(&mut self) -> Result<(), LayerShikaError> {
+        for state in self.states_mut() {
+            state.set_static_rendering(true);
+        }
+        self.run()
+    }
+
+    pub fn render_once(&mut self) {
+        for state in self.states_mut() {
+            state.render_once();
+        }
+    }
+
+    /// Draws a frame on every surface right away, even if nothing changed, and returns only after
+    /// a roundtrip on each surface's queue confirmed the compositor has processed the commits,
+    /// e.g. before taking a screenshot in a visual test. It lives here rather than on the window,
+    /// which has no queue to roundtrip on. Frame callbacks that arrived by then are handled, but
+    /// not waited for, as a hidden surface never gets one. Normal operation goes through `run`,
+    /// which never blocks on the compositor.
+    pub fn render_frame_blocking(&mut self) -> Result<(), LayerShikaError> {
+        for state in self.states_mut() {
+            state.render_once();
+            state
+                .render_frame_if_dirty()
+                .map_err(|e| LayerShikaError::Rendering(e.to_string()))?;
+        }
+        if let Err(e) = self.event_queue.roundtrip(&mut self.state) {
+            return Err(self.protocol_failure(&e));
+        }
+        for mirror in &mut self.mirrors {
+            mirror.roundtrip()?;
+        }
+        Ok(())
+    }
+
+    /// See [`WindowState::set_anchor`]. From Slint callbacks, which can't borrow the windowing
+    /// system, call it on the state through [`Self::event_loop_handle`] instead.
+    pub fn set_anchor(&mut self, anchor: Anchor) {
+        for state in self.states_mut() {
+            state.set_anchor(anchor);
+        }
+    }
+
+    /// See [`WindowState::set_size`]. Fails for a zero width or height, which the renderer
+    /// can't draw at.
+    pub fn set_size(&mut self, width: u32, height: u32) -> Result<(), LayerShikaError> {
+        Self::validate_size(width, height)?;
+        for state in self.states_mut() {
+            state.set_size(width, height);
+        }
+        Ok(())
+    }
+
+    fn validate_size(width: u32, height: u32) -> Result<(), LayerShikaError> {
+        if width == 0 || height == 0 {
+            return Err(LayerShikaError::InvalidInput(format!(
+                "Surface size must not be zero, got {width}x{height}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// See [`WindowState::set_exclusive_zone`].
+    pub fn set_exclusive_zone(&mut self, exclusive_zone: i32) {
+        for state in self.states_mut() {
+            state.set_exclusive_zone(exclusive_zone);
+        }
+    }
+
+    /// See [`WindowState::set_keyboard_interactivity`].
+    pub fn set_keyboard_interactivity(&mut self, keyboard_interactivity: KeyboardInteractivity) {
+        for state in self.states_mut() {
+            state.set_keyboard_interactivity(keyboard_interactivity);
+        }
+    }
+
+    /// See [`WindowState::set_fullscreen_on_output`]. Every surface fills its own output.
+    pub fn set_fullscreen_on_output(&mut self, fullscreen: bool) {
+        for state in self.states_mut() {
+            state.set_fullscreen_on_output(fullscreen);
+        }
+    }
+
+    /// See [`WindowState::set_language`].
+    #[cfg(feature = "unstable-slint-internals")]
+    pub fn set_language(&mut self, language: &str) {
+        for state in self.states_mut() {
+            state.set_language(language);
+        }
+    }
+
+    /// See [`WindowState::set_input_region`].
+    pub fn set_input_region(&mut self, rects: Option<&[(i32, i32, i32, i32)]>) {
+        for state in self.states_mut() {
+            state.set_input_region(rects);
+        }
+    }
+
+    /// See [`WindowState::pause_output`].
+    pub fn pause_output(&mut self, name: &str) {
+        for state in self.states_mut() {
+            state.pause_output(name);
+        }
+    }
+
+    pub fn resume_output(&mut self, name: &str) {
+        for state in self.states_mut() {
+            state.resume_output(name);
+        }
+    }
+
+    /// The state of every surface, the one on the first output first.
+    fn states(&self) -> impl Iterator<Item = &WindowState> {
+        iter::once(&self.state).chain(self.mirrors.iter().map(|mirror| &mirror.state))
+    }
+
+    fn states_mut(&mut self) -> impl Iterator<Item = &mut WindowState> {
+        iter::once(&mut self.state).chain(self.mirrors.iter_mut().map(|mirror| &mut mirror.state))
     }
 
     fn setup_wayland_event_source(&self) -> Result<(), LayerShikaError> {
@@ -224,15 +988,120 @@ impl WindowingSystem {
         Ok(())
     }
 
+    /// Lets the timers of the surfaces on other outputs wake the main loop and draws their first
+    /// frame from the events read so far.
+    fn setup_mirrors(&mut self) -> Result<(), LayerShikaError> {
+        let main_loop = self.event_loop.handle();
+        for mirror in &mut self.mirrors {
+            mirror.wake(&main_loop)?;
+        }
+        Self::dispatch_mirrors(
+            &self.connection,
+            &mut self.state,
+            &mut self.mirrors,
+            self.mirror_factory.as_mut(),
+        );
+        Ok(())
+    }
+
+    /// Hands the events the main loop read to the surfaces on other outputs, creates the ones
+    /// for newly plugged in outputs and drops the closed ones.
+    fn dispatch_mirrors(
+        connection: &Connection,
+        state: &mut WindowState,
+        mirrors: &mut Vec<OutputMirror>,
+        factory: Option<&mut MirrorFactory>,
+    ) {
+        let Some(factory) = factory else {
+            return;
+        };
+        let events = state.take_relayed_events();
+        for mirror in mirrors.iter_mut() {
+            for event in &events {
+                mirror.relay(event);
+            }
+        }
+        // After the existing surfaces had the chance to return to a re-plugged output.
+        for event in &events {
+            factory.handle(event, connection, state, mirrors);
+        }
+        for mirror in mirrors.iter_mut() {
+            if let Err(e) = mirror.dispatch() {
+                error!("Error processing events of a mirrored surface: {e}");
+            }
+        }
+        // A detached mirror stays, to come back when its output does.
+        mirrors.retain(|mirror| !mirror.state.is_closed());
+        if let Err(e) = connection.flush() {
+            error!("Failed to flush the connection: {e}");
+        }
+    }
+
+    fn setup_visibility_timer(&self) -> Result<(), LayerShikaError> {
+        debug!("Setting up visibility timer");
+
+        self.event_loop
+            .handle()
+            .insert_source(
+                Timer::from_duration(VISIBILITY_POLL_INTERVAL),
+                |_, (), shared_data| {
+                    shared_data.check_visibility();
+                    TimeoutAction::ToDuration(VISIBILITY_POLL_INTERVAL)
+                },
+            )
+            .map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+
+        Ok(())
+    }
+
+    fn setup_intervals(&mut self) -> Result<(), LayerShikaError> {
+        let loop_handles: Vec<_> = iter::once(self.event_loop.handle())
+            .chain(self.mirrors.iter().map(OutputMirror::event_loop_handle))
+            .collect();
+        for (interval, callback) in self.intervals.drain(..) {
+            // Every surface runs its own timer, passing its own component to the callback.
+            let callback = Rc::new(RefCell::new(callback));
+            for loop_handle in &loop_handles {
+                Self::insert_interval(loop_handle, interval, Rc::clone(&callback))?;
+            }
+            if let Some(factory) = &mut self.mirror_factory {
+                factory.intervals.push((interval, callback));
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_interval(
+        loop_handle: &LoopHandle<'static, WindowState>,
+        interval: Duration,
+        callback: Rc<RefCell<IntervalCallback>>,
+    ) -> Result<(), LayerShikaError> {
+        loop_handle
+            .insert_source(Timer::from_duration(interval), move |_, (), shared_data| {
+                let keep_running = (callback.borrow_mut())(shared_data.component_instance());
+                shared_data.window().request_redraw();
+                if keep_running {
+                    TimeoutAction::ToDuration(interval)
+                } else {
+                    TimeoutAction::Drop
+                }
+            })
+            .map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+        Ok(())
+    }
+
     fn process_events(
         connection: &Connection,
         event_queue: &mut EventQueue<WindowState>,
         shared_data: &mut WindowState,
     ) -> Result<(), LayerShikaError> {
         if let Some(guard) = event_queue.prepare_read() {
-            guard
-                .read()
-                .map_err(|e| LayerShikaError::WaylandProtocol(e.to_string()))?;
+            // The loop also wakes up for timers, in which case there is nothing to read.
+            match guard.read() {
+                Ok(_) => {}
+                Err(WaylandError::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(LayerShikaError::WaylandProtocol(e.to_string())),
+            }
         }
         connection.flush()?;
 
@@ -243,9 +1112,9 @@ impl WindowingSystem {
         slint::platform::update_timers_and_animations();
 
         shared_data
-            .window()
             .render_frame_if_dirty()
             .map_err(|e| LayerShikaError::Rendering(e.to_string()))?;
+        connection.flush()?;
 
         Ok(())
     }
@@ -254,6 +1123,93 @@ impl WindowingSystem {
         self.state.component_instance()
     }
 
+    /// The component of every surface, the one on the first output first. More than one only
+    /// with [`crate::LayerShika::on_all_outputs`].
+    pub fn component_instances(&self) -> impl Iterator<Item = &ComponentInstance> {
+        self.states().map(WindowState::component_instance)
+    }
+
+    /// Sets a public property of the component on every surface and redraws them, e.g. to push
+    /// the time or battery level into a bar. Fails with [`LayerShikaError::InvalidInput`] for an
+    /// unknown property or a value of the wrong type, which the first surface rejects before any
+    /// other surface is changed.
+    pub fn set_property(&self, name: &str, value: Value) -> Result<(), LayerShikaError> {
+        self.state.set_property(name, value.clone())?;
+        for mirror in &self.mirrors {
+            mirror.state.set_property(name, value.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a public property of the component on the first output. Fails with
+    /// [`LayerShikaError::InvalidInput`] for an unknown property.
+    pub fn get_property(&self, name: &str) -> Result<Value, LayerShikaError> {
+        self.state.get_property(name)
+    }
+
+    /// Every global the compositor advertised at startup as `(interface, version)`, e.g. to
+    /// include in bug reports.
+    pub fn protocol_summary(&self) -> Vec<(String, u32)> {
+        self.advertised_globals.clone()
+    }
+
+    pub fn optional_protocols(&self) -> OptionalProtocols {
+        let has = |name: &str| {
+            self.advertised_globals
+                .iter()
+                .any(|(interface, _)| interface == name)
+        };
+        OptionalProtocols {
+            fractional_scale: has("wp_fractional_scale_manager_v1"),
+            cursor_shape: has("wp_cursor_shape_manager_v1"),
+            viewporter: has("wp_viewporter"),
+        }
+    }
+
+    /// The configuration currently in effect on the first output, reflecting runtime changes
+    /// made through the state. The setters here keep the other surfaces in step with it.
+    pub fn current_config(&self) -> ConfigSnapshot {
+        self.state.config_snapshot()
+    }
+
+    /// Applies the anchor, margin, height, exclusive zone and keyboard interactivity of the
+    /// preset `name` with a single commit. The layer, scale and namespace can't change at runtime
+    /// and are left alone.
+    pub fn apply_layout(&mut self, name: &str) -> Result<(), LayerShikaError> {
+        let layout = self
+            .layouts
+            .get(name)
+            .ok_or_else(|| LayerShikaError::InvalidInput(format!("Unknown layout `{name}`")))?;
+        info!("Applying layout `{name}`");
+        self.state.apply_snapshot(layout);
+        for mirror in &mut self.mirrors {
+            mirror.state.apply_snapshot(layout);
+        }
+        Ok(())
+    }
+
+    pub const fn layouts(&self) -> &LayoutSet {
+        &self.layouts
+    }
+
+    /// The presets passed to `with_layouts`, e.g. to save the current layout as a new one.
+    pub const fn layouts_mut(&mut self) -> &mut LayoutSet {
+        &mut self.layouts
+    }
+
+    /// The scale Slint currently renders at, i.e. buffer pixels per logical pixel. Combines the
+    /// configured scale factor with the render scale and, with `follow_output_scale`, the output
+    /// scale, so it can differ from what was passed to `with_scale_factor`.
+    pub fn scale_factor(&self) -> f32 {
+        self.state.window().scale_factor()
+    }
+
+    /// The surface's OpenGL context, for [`crate::LayerShika::with_shared_gl_context`]. `None`
+    /// while creating it is deferred.
+    pub fn gl_context(&self) -> Option<SharedGlContext> {
+        self.state.window().gl_context()
+    }
+
     pub fn window(&self) -> Rc<FemtoVGWindow> {
         self.state.window()
     }
@@ -261,4 +1217,8 @@ impl WindowingSystem {
     pub const fn state(&self) -> &WindowState {
         &self.state
     }
+
+    pub const fn state_mut(&mut self) -> &mut WindowState {
+        &mut self.state
+    }
 }
@@ -0,0 +1,175 @@
+use log::{debug, info};
+use slint::{LogicalSize, PhysicalSize};
+use wayland_client::{
+    globals::GlobalList,
+    protocol::wl_output::{self, WlOutput},
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+
+use crate::errors::LayerShikaError;
+
+/// What the compositor advertised about one of its outputs, kept for every bound output so the
+/// surface can be moved to another one.
+#[derive(Clone)]
+pub struct TrackedOutput {
+    pub global_name: u32,
+    pub output: WlOutput,
+    pub name: Option<String>,
+    pub make: String,
+    pub model: String,
+    pub description: Option<String>,
+    pub size: PhysicalSize,
+    pub refresh_mhz: Option<u32>,
+    pub scale: i32,
+}
+
+impl TrackedOutput {
+    pub const fn new(global_name: u32, output: WlOutput) -> Self {
+        Self {
+            global_name,
+            output,
+            name: None,
+            make: String::new(),
+            model: String::new(),
+            description: None,
+            size: PhysicalSize::new(0, 0),
+            refresh_mhz: None,
+            scale: 1,
+        }
+    }
+
+    /// The output as passed to an [`OutputMatcher`].
+    #[allow(clippy::cast_precision_loss)]
+    pub fn info(&self) -> OutputInfo {
+        OutputInfo {
+            name: self.name.clone(),
+            make: self.make.clone(),
+            model: self.model.clone(),
+            description: self.description.clone(),
+            logical_size: self.size.to_logical(self.scale.max(1) as f32),
+        }
+    }
+}
+
+/// An output as seen by [`crate::LayerShika::with_output_matcher`], with everything the
+/// compositor advertised up to the output's `done` event.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputInfo {
+    /// Connector name like `DP-1`, from `wl_output` v4.
+    pub name: Option<String>,
+    /// Manufacturer from the monitor's EDID, e.g. `Dell Inc.`.
+    pub make: String,
+    /// Model from the monitor's EDID.
+    pub model: String,
+    /// Human-readable description, from `wl_output` v4.
+    pub description: Option<String>,
+    /// Current mode divided by the output scale.
+    pub logical_size: LogicalSize,
+}
+
+pub type OutputMatcher = Box<dyn Fn(&OutputInfo) -> bool>;
+
+#[derive(Default)]
+struct ProbedOutput {
+    info: OutputInfo,
+    mode: PhysicalSize,
+    scale: i32,
+    done: bool,
+}
+
+/// Collects the properties of outputs bound on a throwaway queue, before the windowing state
+/// they would otherwise be dispatched to exists.
+#[derive(Default)]
+struct OutputProbe {
+    outputs: Vec<(WlOutput, ProbedOutput)>,
+}
+
+impl Dispatch<WlOutput, ()> for OutputProbe {
+    #[allow(clippy::cast_precision_loss)]
+    fn event(
+        state: &mut Self,
+        proxy: &WlOutput,
+        event: <WlOutput as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let Some((_, probed)) = state.outputs.iter_mut().find(|(output, _)| output == proxy) else {
+            return;
+        };
+        match event {
+            wl_output::Event::Geometry { make, model, .. } => {
+                probed.info.make = make;
+                probed.info.model = model;
+            }
+            wl_output::Event::Mode {
+                flags: WEnum::Value(flags),
+                width,
+                height,
+                ..
+            } if flags.contains(wl_output::Mode::Current) => {
+                probed.mode = PhysicalSize::new(
+                    width.try_into().unwrap_or_default(),
+                    height.try_into().unwrap_or_default(),
+                );
+            }
+            wl_output::Event::Scale { factor } => probed.scale = factor,
+            wl_output::Event::Name { name } => probed.info.name = Some(name),
+            wl_output::Event::Description { description } => {
+                probed.info.description = Some(description);
+            }
+            wl_output::Event::Done => {
+                probed.info.logical_size = probed.mode.to_logical(probed.scale.max(1) as f32);
+                probed.done = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the position among the outputs in `globals`, in advertised order, of the first one
+/// `matcher` accepts. Every output is bound on a separate queue and released again once its
+/// properties are known.
+pub fn find_output(
+    connection: &Connection,
+    globals: &GlobalList,
+    matcher: &OutputMatcher,
+) -> Result<usize, LayerShikaError> {
+    let mut event_queue = connection.new_event_queue::<OutputProbe>();
+    let queue_handle = event_queue.handle();
+    let mut probe = OutputProbe::default();
+    globals.contents().with_list(|list| {
+        for global in list
+            .iter()
+            .filter(|global| global.interface == WlOutput::interface().name)
+        {
+            let output = globals.registry().bind::<WlOutput, _, _>(
+                global.name,
+                global.version.min(4),
+                &queue_handle,
+                (),
+            );
+            probe.outputs.push((output, ProbedOutput::default()));
+        }
+    });
+    event_queue
+        .roundtrip(&mut probe)
+        .map_err(|e| LayerShikaError::GlobalInitialization(e.to_string()))?;
+
+    let mut found = None;
+    for (index, (output, probed)) in probe.outputs.iter().enumerate() {
+        // Version 1 outputs have no `done`, their properties are complete after the roundtrip.
+        let complete = probed.done || output.version() < 2;
+        debug!("Probed output {:?}, complete: {complete}", probed.info);
+        if found.is_none() && complete && matcher(&probed.info) {
+            info!("Output matcher picked {:?}", probed.info);
+            found = Some(index);
+        }
+        if output.version() >= 3 {
+            output.release();
+        }
+    }
+    found.ok_or_else(|| {
+        LayerShikaError::WindowConfiguration("No output matches the output matcher".into())
+    })
+}
@@ -0,0 +1,126 @@
+use log::{info, warn};
+use smithay_client_toolkit::reexports::calloop::{
+    channel::{self, Sender},
+    LoopHandle,
+};
+use std::thread;
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::{OwnedValue, Value},
+};
+
+use crate::errors::LayerShikaError;
+
+use super::{color_scheme::ColorScheme, language, state::WindowState};
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SETTINGS_INTERFACE: &str = "org.freedesktop.portal.Settings";
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+
+/// Reports the desktop's light/dark preference from the xdg-desktop-portal settings to the state
+/// driven by each of `loop_handles`, now and whenever it changes.
+///
+/// The portal is queried on a thread of its own so that a slow or missing portal never delays
+/// the first frame; until it answers, styles see `ColorScheme::Unknown`. The thread blocks on the
+/// session bus and only ends with the first change after every surface is gone, which is why
+/// following the portal is opt-in.
+pub fn follow_color_scheme(
+    loop_handles: &[LoopHandle<'static, WindowState>],
+) -> Result<(), LayerShikaError> {
+    let senders = loop_handles
+        .iter()
+        .map(listen)
+        .collect::<Result<Vec<_>, _>>()?;
+    language::note_thread_started();
+    thread::Builder::new()
+        .name("layer-shika-portal".into())
+        .spawn(move || {
+            if let Err(e) = watch(senders) {
+                info!("Desktop color scheme not available: {e}");
+            }
+        })
+        .map_err(|e| LayerShikaError::EventLoop(format!("Failed to start portal thread: {e}")))?;
+    Ok(())
+}
+
+fn listen(
+    loop_handle: &LoopHandle<'static, WindowState>,
+) -> Result<Sender<ColorScheme>, LayerShikaError> {
+    let (sender, receiver) = channel::channel();
+    loop_handle
+        .insert_source(receiver, |event, (), state| {
+            if let channel::Event::Msg(color_scheme) = event {
+                state.set_color_scheme(color_scheme);
+            }
+        })
+        .map_err(|e| LayerShikaError::EventLoop(e.to_string()))?;
+    Ok(sender)
+}
+
+fn watch(mut senders: Vec<Sender<ColorScheme>>) -> zbus::Result<()> {
+    let connection = Connection::session()?;
+    let settings = Proxy::new(
+        &connection,
+        PORTAL_DESTINATION,
+        PORTAL_PATH,
+        SETTINGS_INTERFACE,
+    )?;
+    // Subscribing before reading keeps a change in between from being missed.
+    let changes = settings.receive_signal("SettingChanged")?;
+    let current: OwnedValue = settings.call("Read", &(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY))?;
+    if !report(&mut senders, &current) {
+        return Ok(());
+    }
+    for message in changes {
+        let (namespace, key, value): (String, String, OwnedValue) = match message.body() {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Malformed portal setting change: {e}");
+                continue;
+            }
+        };
+        if namespace == APPEARANCE_NAMESPACE
+            && key == COLOR_SCHEME_KEY
+            && !report(&mut senders, &value)
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Sends the color scheme in `value` to every surface still there, returning whether any is.
+fn report(senders: &mut Vec<Sender<ColorScheme>>, value: &Value<'_>) -> bool {
+    let color_scheme = color_scheme(value);
+    info!("Desktop color scheme is {color_scheme:?}");
+    senders.retain(|sender| sender.send(color_scheme).is_ok());
+    !senders.is_empty()
+}
+
+/// `Read` wraps the value in a second variant, `SettingChanged` doesn't.
+fn color_scheme(value: &Value<'_>) -> ColorScheme {
+    match value {
+        Value::Value(inner) => color_scheme(inner),
+        Value::U32(1) => ColorScheme::Dark,
+        Value::U32(2) => ColorScheme::Light,
+        _ => ColorScheme::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_scheme_reads_plain_and_wrapped_values() {
+        assert_eq!(color_scheme(&Value::U32(1)), ColorScheme::Dark);
+        assert_eq!(
+            color_scheme(&Value::Value(Box::new(Value::U32(2)))),
+            ColorScheme::Light
+        );
+        assert_eq!(color_scheme(&Value::U32(0)), ColorScheme::Unknown);
+        assert_eq!(color_scheme(&Value::from("dark")), ColorScheme::Unknown);
+    }
+}
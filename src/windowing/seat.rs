@@ -0,0 +1,95 @@
+use log::info;
+use wayland_client::{
+    protocol::{
+        wl_keyboard::WlKeyboard,
+        wl_pointer::WlPointer,
+        wl_seat::{Capability, WlSeat},
+        wl_touch::WlTouch,
+    },
+    Proxy, QueueHandle, WEnum,
+};
+
+use super::state::WindowState;
+
+/// Input devices of the seat, created when the seat advertises the matching capability and
+/// released when it loses it, e.g. when a mouse or touchscreen is unplugged.
+pub struct SeatDevices {
+    seat: WlSeat,
+    pointer_enabled: bool,
+    touch_enabled: bool,
+    pointer: Option<WlPointer>,
+    keyboard: Option<WlKeyboard>,
+    touch: Option<WlTouch>,
+}
+
+impl SeatDevices {
+    pub const fn new(seat: WlSeat, pointer_enabled: bool, touch_enabled: bool) -> Self {
+        Self {
+            seat,
+            pointer_enabled,
+            touch_enabled,
+            pointer: None,
+            keyboard: None,
+            touch: None,
+        }
+    }
+
+    /// Creates or releases devices to match `capabilities` from `wl_seat.capabilities`.
+    /// Returns the capabilities whose device went away.
+    pub fn update(
+        &mut self,
+        capabilities: WEnum<Capability>,
+        queue_handle: &QueueHandle<WindowState>,
+    ) -> Capability {
+        let capabilities = match capabilities {
+            WEnum::Value(capabilities) => capabilities,
+            WEnum::Unknown(bits) => Capability::from_bits_truncate(bits),
+        };
+        let mut removed = Capability::empty();
+
+        if capabilities.contains(Capability::Pointer) {
+            if self.pointer_enabled && self.pointer.is_none() {
+                info!("Seat has a pointer, creating pointer device");
+                self.pointer = Some(self.seat.get_pointer(queue_handle, ()));
+            }
+        } else if let Some(pointer) = self.pointer.take() {
+            info!("Seat lost its pointer, releasing pointer device");
+            if pointer.version() >= 3 {
+                pointer.release();
+            }
+            removed |= Capability::Pointer;
+        }
+
+        if capabilities.contains(Capability::Keyboard) {
+            if self.keyboard.is_none() {
+                info!("Seat has a keyboard, creating keyboard device");
+                self.keyboard = Some(self.seat.get_keyboard(queue_handle, ()));
+            }
+        } else if let Some(keyboard) = self.keyboard.take() {
+            info!("Seat lost its keyboard, releasing keyboard device");
+            if keyboard.version() >= 3 {
+                keyboard.release();
+            }
+            removed |= Capability::Keyboard;
+        }
+
+        if capabilities.contains(Capability::Touch) {
+            if self.touch_enabled && self.touch.is_none() {
+                info!("Seat has touch, creating touch device");
+                self.touch = Some(self.seat.get_touch(queue_handle, ()));
+            }
+        } else if let Some(touch) = self.touch.take() {
+            info!("Seat lost touch, releasing touch device");
+            if touch.version() >= 3 {
+                touch.release();
+            }
+            removed |= Capability::Touch;
+        }
+
+        removed
+    }
+
+    pub const fn keyboard(&self) -> Option<&WlKeyboard> {
+        self.keyboard.as_ref()
+    }
+}
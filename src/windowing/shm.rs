@@ -0,0 +1,163 @@
+use crate::errors::LayerShikaError;
+use smithay_client_toolkit::{error::GlobalError, globals::ProvidesBoundGlobal, shm::raw::RawPool};
+use std::{io::Write, num::TryFromIntError};
+use wayland_client::{
+    protocol::{wl_buffer::WlBuffer, wl_shm},
+    QueueHandle,
+};
+
+use super::state::WindowState;
+
+struct ShmGlobal<'a>(&'a wl_shm::WlShm);
+
+impl ProvidesBoundGlobal<wl_shm::WlShm, 1> for ShmGlobal<'_> {
+    fn bound_global(&self) -> Result<wl_shm::WlShm, GlobalError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// A client-rendered image in shared memory, attached to the surface instead of Slint's output.
+/// The pool backing the buffer has to outlive the buffer, so both are kept together.
+pub struct ShmImage {
+    buffer: WlBuffer,
+    _pool: RawPool,
+    released: bool,
+}
+
+impl ShmImage {
+    /// Copies `data`, rows of `width` pixels in `format` with no padding beyond the stride
+    /// implied by its length, into a new shared memory buffer.
+    pub fn new(
+        shm: &wl_shm::WlShm,
+        queue_handle: &QueueHandle<WindowState>,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        format: wl_shm::Format,
+    ) -> Result<Self, LayerShikaError> {
+        let stride = stride(data.len(), width, height, format)?;
+        let too_large =
+            |e: TryFromIntError| LayerShikaError::InvalidInput(format!("Image too large: {e}"));
+
+        let mut pool = RawPool::new(data.len(), &ShmGlobal(shm))
+            .map_err(|e| LayerShikaError::Rendering(format!("Failed to create shm pool: {e}")))?;
+        pool.write_all(data)
+            .map_err(|e| LayerShikaError::Rendering(format!("Failed to write shm pool: {e}")))?;
+        let buffer = pool.create_buffer(
+            0,
+            i32::try_from(width).map_err(too_large)?,
+            i32::try_from(height).map_err(too_large)?,
+            stride,
+            format,
+            (),
+            queue_handle,
+        );
+
+        Ok(Self {
+            buffer,
+            _pool: pool,
+            released: false,
+        })
+    }
+
+    pub const fn buffer(&self) -> &WlBuffer {
+        &self.buffer
+    }
+
+    /// Whether the compositor signalled that it no longer reads from the buffer.
+    pub const fn is_released(&self) -> bool {
+        self.released
+    }
+
+    pub const fn set_released(&mut self) {
+        self.released = true;
+    }
+}
+
+impl Drop for ShmImage {
+    fn drop(&mut self) {
+        self.buffer.destroy();
+    }
+}
+
+/// Bytes per pixel of the single-plane formats `ShmImage` accepts.
+const fn bytes_per_pixel(format: wl_shm::Format) -> Option<u32> {
+    match format {
+        wl_shm::Format::Argb8888
+        | wl_shm::Format::Xrgb8888
+        | wl_shm::Format::Abgr8888
+        | wl_shm::Format::Xbgr8888
+        | wl_shm::Format::Rgba8888
+        | wl_shm::Format::Rgbx8888
+        | wl_shm::Format::Bgra8888
+        | wl_shm::Format::Bgrx8888 => Some(4),
+        wl_shm::Format::Rgb888 | wl_shm::Format::Bgr888 => Some(3),
+        wl_shm::Format::Rgb565 | wl_shm::Format::Bgr565 => Some(2),
+        _ => None,
+    }
+}
+
+/// The stride of `height` rows sharing `len` bytes, checked to hold `width` pixels of `format`.
+/// A shorter one makes the compositor raise `invalid_stride`, which ends the connection.
+fn stride(
+    len: usize,
+    width: u32,
+    height: u32,
+    format: wl_shm::Format,
+) -> Result<i32, LayerShikaError> {
+    let bytes_per_pixel = bytes_per_pixel(format).ok_or_else(|| {
+        LayerShikaError::InvalidInput(format!("Unsupported image format {format:?}"))
+    })?;
+    if width == 0 || height == 0 || len == 0 {
+        return Err(LayerShikaError::InvalidInput(
+            "Image buffer must not be empty".into(),
+        ));
+    }
+    let rows = usize::try_from(height)
+        .map_err(|e| LayerShikaError::InvalidInput(format!("Invalid image height: {e}")))?;
+    if !len.is_multiple_of(rows) {
+        return Err(LayerShikaError::InvalidInput(format!(
+            "Image data of {len} bytes does not split into {height} rows"
+        )));
+    }
+    let too_large =
+        |e: TryFromIntError| LayerShikaError::InvalidInput(format!("Image too large: {e}"));
+    let stride = i32::try_from(len / rows).map_err(too_large)?;
+    let row_len = width
+        .checked_mul(bytes_per_pixel)
+        .and_then(|row_len| i32::try_from(row_len).ok())
+        .ok_or_else(|| LayerShikaError::InvalidInput(format!("Image too wide: {width}")))?;
+    if stride < row_len {
+        return Err(LayerShikaError::InvalidInput(format!(
+            "Image rows of {stride} bytes are too short for {width} pixels in {format:?}"
+        )));
+    }
+    Ok(stride)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stride_follows_the_data_length() {
+        let stride = stride(4 * 10 * 2 + 16, 10, 2, wl_shm::Format::Argb8888);
+        assert_eq!(stride.ok(), Some(48));
+    }
+
+    #[test]
+    fn rejects_rows_shorter_than_the_width() {
+        assert!(matches!(
+            stride(4 * 9 * 2, 10, 2, wl_shm::Format::Xrgb8888),
+            Err(LayerShikaError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_unsupported_formats() {
+        assert!(matches!(
+            stride(4 * 10 * 2, 10, 2, wl_shm::Format::Nv12),
+            Err(LayerShikaError::InvalidInput(_))
+        ));
+    }
+}
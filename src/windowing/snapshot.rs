@@ -0,0 +1,80 @@
+use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::Layer,
+    zwlr_layer_surface_v1::{Anchor, KeyboardInteractivity},
+};
+
+/// The effective layer surface configuration, including changes made at runtime.
+///
+/// With the `serde` feature it can be saved and later passed back to
+/// [`crate::LayerShika::with_config_snapshot`] to restore the layout.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigSnapshot {
+    #[cfg_attr(feature = "serde", serde(with = "serde_layer"))]
+    pub layer: Layer,
+    #[cfg_attr(feature = "serde", serde(with = "serde_anchor"))]
+    pub anchor: Anchor,
+    /// `(top, right, bottom, left)`
+    pub margin: (i32, i32, i32, i32),
+    pub height: u32,
+    pub exclusive_zone: i32,
+    #[cfg_attr(feature = "serde", serde(with = "serde_keyboard_interactivity"))]
+    pub keyboard_interactivity: KeyboardInteractivity,
+    pub scale_factor: f32,
+    pub namespace: String,
+}
+
+// The protocol types carry no serde support, so they are stored as their wire values.
+
+#[cfg(feature = "serde")]
+mod serde_layer {
+    use super::Layer;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S: Serializer>(layer: &Layer, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(u32::from(*layer))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Layer, D::Error> {
+        let value = u32::deserialize(deserializer)?;
+        Layer::try_from(value).map_err(|()| D::Error::custom(format!("invalid layer {value}")))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_anchor {
+    use super::Anchor;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S: Serializer>(anchor: &Anchor, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(anchor.bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Anchor, D::Error> {
+        u32::deserialize(deserializer).map(Anchor::from_bits_truncate)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_keyboard_interactivity {
+    use super::KeyboardInteractivity;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S: Serializer>(
+        interactivity: &KeyboardInteractivity,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(u32::from(*interactivity))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<KeyboardInteractivity, D::Error> {
+        let value = u32::deserialize(deserializer)?;
+        KeyboardInteractivity::try_from(value)
+            .map_err(|()| D::Error::custom(format!("invalid keyboard interactivity {value}")))
+    }
+}
@@ -0,0 +1,98 @@
+use log::{info, warn};
+use slint_interpreter::{CompilationResult, Compiler, ComponentDefinition, DiagnosticLevel};
+use std::path::PathBuf;
+
+use crate::errors::LayerShikaError;
+
+/// File name inline markup is compiled as: it appears in diagnostics, and relative imports
+/// resolve against the working directory.
+const INLINE_SOURCE_PATH: &str = "inline.slint";
+
+/// Slint markup the component is compiled from when the windowing system is built, see
+/// `with_component_path` and `with_component_source`.
+#[derive(Clone)]
+pub enum ComponentSource {
+    Path(PathBuf),
+    Inline(String),
+}
+
+impl ComponentSource {
+    /// Compiles the markup and picks the exported component called `name`, or the only one
+    /// exported when no name is given. Imports are also looked up in `include_paths`.
+    pub fn compile(
+        &self,
+        name: Option<&str>,
+        include_paths: &[PathBuf],
+    ) -> Result<ComponentDefinition, LayerShikaError> {
+        let mut compiler = Compiler::default();
+        compiler.set_include_paths(include_paths.to_vec());
+        // Without a custom file loader the compiler never actually suspends.
+        let result = match self {
+            Self::Path(path) => {
+                info!("Compiling Slint component from {}", path.display());
+                spin_on::spin_on(compiler.build_from_path(path))
+            }
+            Self::Inline(source) => {
+                info!("Compiling inline Slint component");
+                spin_on::spin_on(
+                    compiler.build_from_source(source.clone(), PathBuf::from(INLINE_SOURCE_PATH)),
+                )
+            }
+        };
+        if result.has_errors() {
+            return Err(LayerShikaError::SlintComponentCreation(format_diagnostics(
+                &result,
+                DiagnosticLevel::Error,
+            )));
+        }
+        if result.diagnostics().next().is_some() {
+            warn!(
+                "Slint compiler warnings:\n{}",
+                format_diagnostics(&result, DiagnosticLevel::Warning)
+            );
+        }
+        select_component(&result, name)
+    }
+}
+
+fn select_component(
+    result: &CompilationResult,
+    name: Option<&str>,
+) -> Result<ComponentDefinition, LayerShikaError> {
+    if let Some(name) = name {
+        return result.component(name).ok_or_else(|| {
+            LayerShikaError::SlintComponentCreation(format!(
+                "No exported component named `{name}`, available: {}",
+                component_names(result)
+            ))
+        });
+    }
+    // The compiler hands out components unordered, so which one is meant can't be guessed.
+    let mut components = result.components();
+    match (components.next(), components.next()) {
+        (Some(component), None) => Ok(component),
+        (None, _) => Err(LayerShikaError::SlintComponentCreation(
+            "The Slint markup exports no component".into(),
+        )),
+        (Some(_), Some(_)) => Err(LayerShikaError::SlintComponentCreation(format!(
+            "The Slint markup exports several components ({}), pick one with with_component_name",
+            component_names(result)
+        ))),
+    }
+}
+
+fn component_names(result: &CompilationResult) -> String {
+    let mut names: Vec<_> = result.component_names().collect();
+    names.sort_unstable();
+    names.join(", ")
+}
+
+/// One diagnostic of `level` per line, with file and line number.
+fn format_diagnostics(result: &CompilationResult, level: DiagnosticLevel) -> String {
+    result
+        .diagnostics()
+        .filter(|diagnostic| diagnostic.level() == level)
+        .map(|diagnostic| diagnostic.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,86 @@
+use log::{info, warn};
+use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::zwlr_layer_shell_v1::Layer;
+use std::cell::{Cell, RefCell};
+
+// Layer-shell has no request to order surfaces within a layer. In practice compositors stack a
+// newly mapped surface above the ones already on its layer, so creation order is the only lever;
+// this registry tracks the surfaces created by this thread, and has the ones with a higher
+// `z_hint` than a newly created surface re-map themselves on top of it.
+thread_local! {
+    static SURFACES: RefCell<Vec<StackEntry>> = const { RefCell::new(Vec::new()) };
+    static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+struct StackEntry {
+    id: u64,
+    layer: Layer,
+    z_hint: i32,
+    restack: bool,
+}
+
+/// Registration of a surface in the per-thread stacking order, removed again on drop.
+pub struct StackingSlot {
+    id: u64,
+}
+
+impl StackingSlot {
+    /// Registers a surface about to be created on `layer`. Surfaces created earlier on the same
+    /// layer with a higher `z_hint` are marked to re-map, see [`Self::take_restack`].
+    pub fn register(layer: Layer, z_hint: i32) -> Self {
+        let id = NEXT_ID.replace(NEXT_ID.get() + 1);
+        SURFACES.with_borrow_mut(|surfaces| {
+            let mut above = surfaces
+                .iter_mut()
+                .filter(|entry| entry.layer == layer && entry.z_hint > z_hint)
+                .peekable();
+            if above.peek().is_some() {
+                warn!(
+                    "Surface with z_hint {z_hint} is created after surfaces with a higher z_hint on the {layer:?} layer; re-mapping them on top, which not every compositor honors. Create surfaces in ascending z_hint order to avoid it."
+                );
+                for entry in above {
+                    entry.restack = true;
+                }
+            } else {
+                info!("Stacking surface with z_hint {z_hint} on the {layer:?} layer");
+            }
+            surfaces.push(StackEntry {
+                id,
+                layer,
+                z_hint,
+                restack: false,
+            });
+        });
+        Self { id }
+    }
+
+    /// Whether the surface should re-map now to get back above a surface created after it.
+    /// Surfaces with a lower `z_hint` go first, so re-mapping recreates them in ascending order.
+    pub fn take_restack(&self) -> bool {
+        SURFACES.with_borrow_mut(|surfaces| {
+            let Some(own) = surfaces.iter().find(|entry| entry.id == self.id) else {
+                return false;
+            };
+            let (layer, z_hint) = (own.layer, own.z_hint);
+            let ready = own.restack
+                && !surfaces.iter().any(|entry| {
+                    entry.restack
+                        && entry.layer == layer
+                        && (entry.z_hint, entry.id) < (z_hint, self.id)
+                });
+            if let Some(own) = surfaces
+                .iter_mut()
+                .find(|entry| entry.id == self.id)
+                .filter(|_| ready)
+            {
+                own.restack = false;
+            }
+            ready
+        })
+    }
+}
+
+impl Drop for StackingSlot {
+    fn drop(&mut self) {
+        SURFACES.with_borrow_mut(|surfaces| surfaces.retain(|entry| entry.id != self.id));
+    }
+}
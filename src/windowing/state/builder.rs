@@ -1,23 +1,67 @@
-use std::rc::Rc;
+use std::{rc::Rc, time::Duration};
 use slint::PhysicalSize;
-use slint_interpreter::ComponentDefinition;
-use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::ZwlrLayerSurfaceV1;
-use wayland_client::protocol::{wl_pointer::WlPointer, wl_surface::WlSurface};
-use crate::{errors::LayerShikaError, rendering::{femtovg_window::FemtoVGWindow, slint_platform::CustomSlintPlatform}};
+use smithay_client_toolkit::reexports::calloop::{LoopHandle, LoopSignal};
+use slint_interpreter::{ComponentDefinition, Value};
+use smithay_client_toolkit::reexports::protocols::wp::fractional_scale::v1::client::{wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1, wp_fractional_scale_v1::WpFractionalScaleV1};
+use smithay_client_toolkit::reexports::protocols::wp::viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter};
+use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1};
+use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::{Anchor, KeyboardInteractivity};
+use wayland_client::{
+    protocol::{
+        wl_compositor::WlCompositor, wl_output::WlOutput, wl_shm::WlShm, wl_seat::WlSeat,
+        wl_surface::WlSurface,
+    },
+    QueueHandle,
+};
+use crate::{errors::LayerShikaError, rendering::{egl_context::EGLContextBuilder, femtovg_window::FemtoVGWindow, slint_platform::CustomSlintPlatform}, windowing::{callbacks::WindowCallbacks, config::{ConfigureBehavior, HeightMode, OutputLossBehavior}, layer_surface::LayerSurface, mirror::SharedGlobals, outputs::{OutputMatcher, TrackedOutput}, seat::SeatDevices}};
 
 use super::WindowState;
 
 pub struct WindowStateBuilder {
     pub component_definition: Option<ComponentDefinition>,
+    pub properties: Vec<(String, Value)>,
+    pub initial_focus: Option<String>,
+    pub compositor: Option<Rc<WlCompositor>>,
+    pub shm: Option<WlShm>,
+    pub queue_handle: Option<QueueHandle<WindowState>>,
     pub surface: Option<Rc<WlSurface>>,
-    pub layer_surface: Option<Rc<ZwlrLayerSurfaceV1>>,
+    pub layer_surface: Option<Rc<LayerSurface>>,
+    pub layer_shell: Option<ZwlrLayerShellV1>,
+    pub output: Option<WlOutput>,
+    pub outputs: Vec<TrackedOutput>,
+    pub output_matcher: Option<OutputMatcher>,
+    pub shared_globals: SharedGlobals,
+    pub layer: Layer,
+    pub viewport: Option<WpViewport>,
+    pub viewporter: Option<WpViewporter>,
+    pub fractional_scale: Option<(WpFractionalScaleManagerV1, WpFractionalScaleV1)>,
     pub size: Option<PhysicalSize>,
     pub output_size: Option<PhysicalSize>,
-    pub pointer: Option<Rc<WlPointer>>,
+    pub seat_devices: Option<SeatDevices>,
     pub window: Option<Rc<FemtoVGWindow>>,
+    pub pending_context: Option<EGLContextBuilder>,
+    pub context_builder: Option<EGLContextBuilder>,
     pub scale_factor: f32,
+    pub render_scale: f32,
+    pub follow_output_scale: bool,
+    pub configure_diagnostics: bool,
     pub height: u32,
+    pub height_mode: HeightMode,
+    pub width: Option<u32>,
     pub exclusive_zone: i32,
+    pub max_exclusive_fraction: Option<f32>,
+    pub configure_behavior: ConfigureBehavior,
+    pub output_loss_behavior: OutputLossBehavior,
+    pub anchor: Anchor,
+    pub margin: (i32, i32, i32, i32),
+    pub keyboard_interactivity: KeyboardInteractivity,
+    pub hot_edge: Option<u32>,
+    pub opaque: bool,
+    pub auto_close: Option<Duration>,
+    pub loop_handle: Option<LoopHandle<'static, WindowState>>,
+    pub loop_signal: Option<LoopSignal>,
+    pub namespace: String,
+    pub callbacks: WindowCallbacks,
 }
 
 impl WindowStateBuilder {
@@ -26,6 +70,24 @@ impl WindowStateBuilder {
         Self::default()
     }
 
+    #[must_use]
+    pub fn with_compositor(mut self, compositor: Rc<WlCompositor>) -> Self {
+        self.compositor = Some(compositor);
+        self
+    }
+
+    #[must_use]
+    pub fn with_shm(mut self, shm: WlShm) -> Self {
+        self.shm = Some(shm);
+        self
+    }
+
+    #[must_use]
+    pub fn with_queue_handle(mut self, queue_handle: QueueHandle<WindowState>) -> Self {
+        self.queue_handle = Some(queue_handle);
+        self
+    }
+
     #[must_use]
     pub fn with_surface(mut self, surface: Rc<WlSurface>) -> Self {
         self.surface = Some(surface);
@@ -33,11 +95,85 @@ impl WindowStateBuilder {
     }
 
     #[must_use]
-    pub fn with_layer_surface(mut self, layer_surface: Rc<ZwlrLayerSurfaceV1>) -> Self {
+    pub fn with_layer_surface(mut self, layer_surface: Rc<LayerSurface>) -> Self {
         self.layer_surface = Some(layer_surface);
         self
     }
 
+    #[must_use]
+    pub const fn with_layer(mut self, layer: Layer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    #[must_use]
+    pub fn with_layer_shell(mut self, layer_shell: ZwlrLayerShellV1) -> Self {
+        self.layer_shell = Some(layer_shell);
+        self
+    }
+
+    /// The output the surface is created on, and all outputs it may move to.
+    #[must_use]
+    pub fn with_outputs(mut self, output: WlOutput, outputs: Vec<TrackedOutput>) -> Self {
+        self.output = Some(output);
+        self.outputs = outputs;
+        self
+    }
+
+    /// Picks the output to move to or return to when the surface loses its output, see
+    /// `WindowState::handle_closed`.
+    #[must_use]
+    pub fn with_output_matcher(mut self, output_matcher: Option<OutputMatcher>) -> Self {
+        self.output_matcher = output_matcher;
+        self
+    }
+
+    /// Whether the outputs and seat are shared with the surfaces on other outputs.
+    #[must_use]
+    pub fn with_shared_globals(mut self, shared_globals: SharedGlobals) -> Self {
+        self.shared_globals = shared_globals;
+        self
+    }
+
+    #[must_use]
+    pub fn with_viewporter(mut self, viewporter: Option<WpViewporter>) -> Self {
+        self.viewporter = viewporter;
+        self
+    }
+
+    #[must_use]
+    pub fn with_viewport(mut self, viewport: Option<WpViewport>) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    #[must_use]
+    pub fn with_fractional_scale(
+        mut self,
+        fractional_scale: Option<(WpFractionalScaleManagerV1, WpFractionalScaleV1)>,
+    ) -> Self {
+        self.fractional_scale = fractional_scale;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_render_scale(mut self, render_scale: f32) -> Self {
+        self.render_scale = render_scale;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_configure_diagnostics(mut self, configure_diagnostics: bool) -> Self {
+        self.configure_diagnostics = configure_diagnostics;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_follow_output_scale(mut self, follow_output_scale: bool) -> Self {
+        self.follow_output_scale = follow_output_scale;
+        self
+    }
+
     #[must_use]
     pub const fn with_size(mut self, size: PhysicalSize) -> Self {
         self.size = Some(size);
@@ -50,9 +186,11 @@ impl WindowStateBuilder {
         self
     }
 
+    /// The seat input devices are created from once it advertises them; pointer and touch only
+    /// if enabled.
     #[must_use]
-    pub fn with_pointer(mut self, pointer: Rc<WlPointer>) -> Self {
-        self.pointer = Some(pointer);
+    pub fn with_seat(mut self, seat: WlSeat, pointer_enabled: bool, touch_enabled: bool) -> Self {
+        self.seat_devices = Some(SeatDevices::new(seat, pointer_enabled, touch_enabled));
         self
     }
 
@@ -62,6 +200,19 @@ impl WindowStateBuilder {
         self
     }
 
+    /// Template for the EGL contexts of surfaces re-created on another output.
+    #[must_use]
+    pub fn with_context_builder(mut self, context_builder: EGLContextBuilder) -> Self {
+        self.context_builder = Some(context_builder);
+        self
+    }
+
+    #[must_use]
+    pub fn with_pending_context(mut self, pending_context: Option<EGLContextBuilder>) -> Self {
+        self.pending_context = pending_context;
+        self
+    }
+
     #[must_use]
     pub const fn with_scale_factor(mut self, scale_factor: f32) -> Self {
         self.scale_factor = scale_factor;
@@ -74,27 +225,132 @@ impl WindowStateBuilder {
         self
     }
 
+    #[must_use]
+    pub const fn with_width(mut self, width: Option<u32>) -> Self {
+        self.width = width;
+        self
+    }
+
     #[must_use]
     pub const fn with_exclusive_zone(mut self, exclusive_zone: i32) -> Self {
         self.exclusive_zone = exclusive_zone;
         self
     }
 
+    #[must_use]
+    pub const fn with_anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_margin(mut self, margin: (i32, i32, i32, i32)) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_keyboard_interactivity(
+        mut self,
+        keyboard_interactivity: KeyboardInteractivity,
+    ) -> Self {
+        self.keyboard_interactivity = keyboard_interactivity;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_max_exclusive_fraction(mut self, fraction: Option<f32>) -> Self {
+        self.max_exclusive_fraction = fraction;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_hot_edge(mut self, hot_edge: Option<u32>) -> Self {
+        self.hot_edge = hot_edge;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_opaque(mut self, opaque: bool) -> Self {
+        self.opaque = opaque;
+        self
+    }
+
+    #[must_use]
+    pub fn with_namespace(mut self, namespace: String) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    #[must_use]
+    pub fn with_callbacks(mut self, callbacks: WindowCallbacks) -> Self {
+        self.callbacks = callbacks;
+        self
+    }
+
+    #[must_use]
+    pub fn with_properties(mut self, properties: Vec<(String, Value)>) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    #[must_use]
+    pub fn with_initial_focus(mut self, initial_focus: Option<String>) -> Self {
+        self.initial_focus = initial_focus;
+        self
+    }
+
     #[must_use]
     pub fn with_component_definition(mut self, component_definition: ComponentDefinition) -> Self {
         self.component_definition = Some(component_definition);
         self
     }
 
+    #[must_use]
+    pub fn with_event_loop(
+        mut self,
+        loop_handle: LoopHandle<'static, WindowState>,
+        loop_signal: LoopSignal,
+    ) -> Self {
+        self.loop_handle = Some(loop_handle);
+        self.loop_signal = Some(loop_signal);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_configure_behavior(mut self, configure_behavior: ConfigureBehavior) -> Self {
+        self.configure_behavior = configure_behavior;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_height_mode(mut self, height_mode: HeightMode) -> Self {
+        self.height_mode = height_mode;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_output_loss_behavior(
+        mut self,
+        output_loss_behavior: OutputLossBehavior,
+    ) -> Self {
+        self.output_loss_behavior = output_loss_behavior;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_auto_close(mut self, auto_close: Option<Duration>) -> Self {
+        self.auto_close = auto_close;
+        self
+    }
+
     pub fn build(self) -> Result<WindowState, LayerShikaError> {
-        let platform = CustomSlintPlatform::new(Rc::clone(
+        CustomSlintPlatform::install()?;
+        CustomSlintPlatform::register_window(
             self.window
                 .as_ref()
                 .ok_or_else(|| LayerShikaError::InvalidInput("Window is required".into()))?,
-        ));
-        slint::platform::set_platform(Box::new(platform)).map_err(|e| {
-            LayerShikaError::PlatformSetup(format!("Failed to set platform: {e:?}"))
-        })?;
+        );
 
         WindowState::new(self)
     }
@@ -104,15 +360,49 @@ impl Default for WindowStateBuilder {
     fn default() -> Self {
         Self {
             component_definition: None,
+            properties: Vec::new(),
+            initial_focus: None,
+            compositor: None,
+            shm: None,
+            queue_handle: None,
             surface: None,
             layer_surface: None,
+            layer_shell: None,
+            output: None,
+            outputs: Vec::new(),
+            output_matcher: None,
+            shared_globals: SharedGlobals::Own,
+            layer: Layer::Top,
+            viewport: None,
+            viewporter: None,
+            fractional_scale: None,
             size: None,
             output_size: None,
-            pointer: None,
+            seat_devices: None,
             window: None,
+            pending_context: None,
+            context_builder: None,
             scale_factor: 1.0,
+            render_scale: 1.0,
+            follow_output_scale: false,
+            configure_diagnostics: false,
             height: 30,
+            height_mode: HeightMode::default(),
+            width: None,
             exclusive_zone: -1,
+            max_exclusive_fraction: None,
+            configure_behavior: ConfigureBehavior::default(),
+            output_loss_behavior: OutputLossBehavior::default(),
+            anchor: Anchor::Top | Anchor::Left | Anchor::Right,
+            margin: (0, 0, 0, 0),
+            keyboard_interactivity: KeyboardInteractivity::OnDemand,
+            hot_edge: None,
+            opaque: false,
+            auto_close: None,
+            loop_handle: None,
+            loop_signal: None,
+            namespace: "layer-shika".to_owned(),
+            callbacks: WindowCallbacks::default(),
         }
     }
 }
@@ -0,0 +1,44 @@
+use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::{
+    Anchor, KeyboardInteractivity,
+};
+
+/// Layer surface changes collected by [`super::WindowState::configure`] and committed together.
+#[derive(Default)]
+pub struct LayerSurfaceChanges {
+    pub(super) anchor: Option<Anchor>,
+    pub(super) margin: Option<(i32, i32, i32, i32)>,
+    pub(super) exclusive_zone: Option<i32>,
+    pub(super) keyboard_interactivity: Option<KeyboardInteractivity>,
+    pub(super) height: Option<u32>,
+}
+
+impl LayerSurfaceChanges {
+    pub const fn anchor(&mut self, anchor: Anchor) -> &mut Self {
+        self.anchor = Some(anchor);
+        self
+    }
+
+    /// Margins in `(top, right, bottom, left)` order.
+    pub const fn margin(&mut self, top: i32, right: i32, bottom: i32, left: i32) -> &mut Self {
+        self.margin = Some((top, right, bottom, left));
+        self
+    }
+
+    pub const fn exclusive_zone(&mut self, exclusive_zone: i32) -> &mut Self {
+        self.exclusive_zone = Some(exclusive_zone);
+        self
+    }
+
+    pub const fn keyboard_interactivity(
+        &mut self,
+        keyboard_interactivity: KeyboardInteractivity,
+    ) -> &mut Self {
+        self.keyboard_interactivity = Some(keyboard_interactivity);
+        self
+    }
+
+    pub const fn height(&mut self, height: u32) -> &mut Self {
+        self.height = Some(height);
+        self
+    }
+}
@@ -1,9 +1,19 @@
-use crate::impl_empty_dispatch;
-use log::info;
+use crate::{
+    impl_empty_dispatch,
+    windowing::{input::InputSource, mirror::SharedEvent},
+};
+use log::{error, info};
 use slint::{
     platform::{PointerEventButton, WindowEvent},
     PhysicalSize,
 };
+use smithay_client_toolkit::reexports::protocols::wp::{
+    fractional_scale::v1::client::{
+        wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+        wp_fractional_scale_v1::{self, WpFractionalScaleV1},
+    },
+    viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter},
+};
 use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::{
     zwlr_layer_shell_v1::ZwlrLayerShellV1,
     zwlr_layer_surface_v1::{self, ZwlrLayerSurfaceV1},
@@ -12,18 +22,29 @@ use wayland_client::WEnum;
 use wayland_client::{
     globals::GlobalListContents,
     protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_callback::{self, WlCallback},
         wl_compositor::WlCompositor,
+        wl_keyboard::{self, WlKeyboard},
         wl_output::{self, WlOutput},
         wl_pointer::{self, WlPointer},
-        wl_registry::WlRegistry,
-        wl_seat::WlSeat,
-        wl_surface::WlSurface,
+        wl_region::WlRegion,
+        wl_registry::{self, WlRegistry},
+        wl_seat::{self, WlSeat},
+        wl_shm::WlShm,
+        wl_surface::{self, WlSurface},
+        wl_touch::{self, WlTouch},
     },
     Connection, Dispatch, Proxy, QueueHandle,
 };
 
 use super::WindowState;
 
+/// Evdev codes of the pointer buttons Slint distinguishes, from `linux/input-event-codes.h`.
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
 impl Dispatch<ZwlrLayerSurfaceV1, ()> for WindowState {
     fn event(
         state: &mut Self,
@@ -41,15 +62,11 @@ impl Dispatch<ZwlrLayerSurfaceV1, ()> for WindowState {
             } => {
                 info!("Layer surface configured with size: {}x{}", width, height);
                 layer_surface.ack_configure(serial);
-                if width > 0 && height > 0 {
-                    state.update_size(state.output_size().width, state.height());
-                } else {
-                    let current_size = state.output_size();
-                    state.update_size(current_size.width, current_size.height);
-                }
+                state.handle_configure(width, height);
             }
             zwlr_layer_surface_v1::Event::Closed => {
                 info!("Layer surface closed");
+                state.handle_closed();
             }
             _ => {}
         }
@@ -59,104 +76,479 @@ impl Dispatch<ZwlrLayerSurfaceV1, ()> for WindowState {
 impl Dispatch<WlOutput, ()> for WindowState {
     fn event(
         state: &mut Self,
-        _proxy: &WlOutput,
+        proxy: &WlOutput,
         event: <WlOutput as Proxy>::Event,
         _data: &(),
         _conn: &Connection,
         _qhandle: &QueueHandle<Self>,
     ) {
-        match event {
-            wl_output::Event::Mode { width, height, .. } => {
-                info!("WlOutput size changed to {}x{}", width, height);
-                let width = width.try_into().unwrap_or_default();
-                let height = height.try_into().unwrap_or_default();
-                state.set_output_size(PhysicalSize::new(width, height));
-            }
-            wl_output::Event::Description { ref description } => {
-                info!("WlOutput description: {:?}", description);
-            }
-            wl_output::Event::Scale { ref factor } => {
-                info!("WlOutput factor scale: {:?}", factor);
-            }
-            wl_output::Event::Name { ref name } => {
-                info!("WlOutput name: {:?}", name);
-            }
-            wl_output::Event::Geometry {
-                x,
-                y,
-                physical_width,
-                physical_height,
-                subpixel,
-                make,
-                model,
-                transform,
-            } => {
-                info!("WlOutput geometry: x={}, y={}, physical_width={}, physical_height={}, subpixel={:?}, make={:?}, model={:?}, transform={:?}", x, y, physical_width, physical_height, subpixel, make, model, transform);
+        output_event(state, proxy, &event);
+        state.relay(SharedEvent::Output(proxy.clone(), event));
+    }
+}
+
+fn output_event(state: &mut WindowState, proxy: &WlOutput, event: &wl_output::Event) {
+    // Every output is tracked, but only the one the surface is on drives its state.
+    let current = state.is_current_output(proxy);
+    match *event {
+        wl_output::Event::Mode {
+            flags,
+            width,
+            height,
+            refresh,
+        } => {
+            // Outputs may list every mode they support; only the current one applies.
+            if !matches!(flags, WEnum::Value(flags) if flags.contains(wl_output::Mode::Current)) {
+                return;
             }
-            wl_output::Event::Done => {
-                info!("WlOutput done");
+            info!(
+                "WlOutput size changed to {}x{} at {} mHz",
+                width, height, refresh
+            );
+            let size = PhysicalSize::new(
+                width.try_into().unwrap_or_default(),
+                height.try_into().unwrap_or_default(),
+            );
+            let refresh_mhz = u32::try_from(refresh).ok().filter(|&r| r > 0);
+            if let Some(info) = state.output_info_mut(proxy) {
+                info.size = size;
+                info.refresh_mhz = refresh_mhz;
+            }
+            if current {
+                state.set_output_size(size);
+                state.set_output_refresh_mhz(refresh_mhz);
+            }
+        }
+        wl_output::Event::Description { ref description } => {
+            info!("WlOutput description: {:?}", description);
+            if let Some(info) = state.output_info_mut(proxy) {
+                info.description = Some(description.clone());
+            }
+        }
+        wl_output::Event::Scale { factor } => {
+            info!("WlOutput factor scale: {:?}", factor);
+            if let Some(info) = state.output_info_mut(proxy) {
+                info.scale = factor;
+            }
+            if current {
+                state.set_output_scale(factor);
+            }
+        }
+        wl_output::Event::Name { ref name } => {
+            info!("WlOutput name: {:?}", name);
+            if let Some(info) = state.output_info_mut(proxy) {
+                info.name = Some(name.clone());
+            }
+            if current {
+                state.set_output_name(name.clone());
             }
-            _ => {}
         }
+        wl_output::Event::Geometry {
+            x,
+            y,
+            physical_width,
+            physical_height,
+            subpixel,
+            ref make,
+            ref model,
+            transform,
+        } => {
+            info!("WlOutput geometry: x={}, y={}, physical_width={}, physical_height={}, subpixel={:?}, make={:?}, model={:?}, transform={:?}", x, y, physical_width, physical_height, subpixel, make, model, transform);
+            if let Some(info) = state.output_info_mut(proxy) {
+                info.make.clone_from(make);
+                info.model.clone_from(model);
+            }
+        }
+        wl_output::Event::Done => {
+            info!("WlOutput done");
+            state.schedule_outputs_settled();
+            state.handle_output_done(proxy);
+            if current {
+                state.apply_output_scale();
+                state.check_scale_mismatch();
+                state.check_scale_conflict();
+            }
+        }
+        _ => {}
     }
 }
 
 impl Dispatch<WlPointer, ()> for WindowState {
     fn event(
         state: &mut Self,
-        _proxy: &WlPointer,
+        proxy: &WlPointer,
         event: <WlPointer as Proxy>::Event,
         _data: &(),
         _conn: &Connection,
         _qhandle: &QueueHandle<Self>,
     ) {
-        match event {
-            wl_pointer::Event::Enter {
-                surface_x,
-                surface_y,
-                ..
-            }
-            | wl_pointer::Event::Motion {
-                surface_x,
-                surface_y,
-                ..
-            } => {
-                state.set_current_pointer_position(surface_x, surface_y);
-                let logical_position = state.current_pointer_position();
-                state.window().dispatch_event(WindowEvent::PointerMoved {
-                    position: *logical_position,
-                });
+        pointer_event(state, proxy, &event);
+        state.relay(SharedEvent::Pointer(proxy.clone(), event));
+    }
+}
+
+fn pointer_event(state: &mut WindowState, proxy: &WlPointer, event: &wl_pointer::Event) {
+    // Events other than enter are only ours while the pointer is over our surface.
+    if !state.has_pointer_focus()
+        && !matches!(
+            event,
+            wl_pointer::Event::Enter { .. } | wl_pointer::Event::Frame
+        )
+    {
+        return;
+    }
+    match *event {
+        wl_pointer::Event::Enter {
+            ref surface,
+            surface_x,
+            surface_y,
+            ..
+        } => {
+            if !state.is_own_surface(surface) {
+                return;
             }
+            state.set_pointer_focus(true);
+            state.reveal_hot_edge();
+            state.pause_auto_close();
+            state.set_current_pointer_position(surface_x, surface_y);
+            let logical_position = state.current_pointer_position();
+            state.queue_pointer_event(WindowEvent::PointerMoved {
+                position: *logical_position,
+            });
+        }
+
+        wl_pointer::Event::Motion {
+            surface_x,
+            surface_y,
+            ..
+        } => {
+            state.set_current_pointer_position(surface_x, surface_y);
+            let logical_position = state.current_pointer_position();
+            state.queue_pointer_event(WindowEvent::PointerMoved {
+                position: *logical_position,
+            });
+        }
+
+        wl_pointer::Event::Leave { .. } => {
+            state.set_pointer_focus(false);
+            state.queue_pointer_event(WindowEvent::PointerExited);
+            state.conceal_hot_edge();
+            state.start_auto_close();
+        }
+
+        wl_pointer::Event::Button {
+            serial,
+            button,
+            state: button_state,
+            ..
+        } => {
+            state.set_last_pointer_serial(serial);
+            let button = pointer_button(button);
+            let event = match button_state {
+                WEnum::Value(wl_pointer::ButtonState::Pressed) => WindowEvent::PointerPressed {
+                    button,
+                    position: *state.current_pointer_position(),
+                },
+                _ => WindowEvent::PointerReleased {
+                    button,
+                    position: *state.current_pointer_position(),
+                },
+            };
+            state.queue_pointer_event(event);
+        }
+
+        wl_pointer::Event::Axis {
+            axis: WEnum::Value(axis),
+            value,
+            ..
+        } => state.handle_pointer_axis(axis, value),
+        // Sent from v5 up to v7, replaced by `axis_value120` from v8 on.
+        wl_pointer::Event::AxisDiscrete {
+            axis: WEnum::Value(axis),
+            discrete,
+        } => state.handle_pointer_axis_value120(axis, discrete.saturating_mul(120)),
+        wl_pointer::Event::AxisValue120 {
+            axis: WEnum::Value(axis),
+            value120,
+        } => state.handle_pointer_axis_value120(axis, value120),
+
+        wl_pointer::Event::Frame => state.flush_pointer_frame(),
+        _ => {}
+    }
+    // Frames only exist from wl_pointer v5 on; older pointers deliver every event on its own.
+    if proxy.version() < 5 {
+        state.flush_pointer_frame();
+    }
+}
+
+/// Maps the evdev button code of `wl_pointer.button` to the Slint button.
+const fn pointer_button(button: u32) -> PointerEventButton {
+    match button {
+        BTN_LEFT => PointerEventButton::Left,
+        BTN_RIGHT => PointerEventButton::Right,
+        BTN_MIDDLE => PointerEventButton::Middle,
+        _ => PointerEventButton::Other,
+    }
+}
+
+impl Dispatch<WlTouch, ()> for WindowState {
+    fn event(
+        state: &mut Self,
+        _proxy: &WlTouch,
+        event: <WlTouch as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        touch_event(state, &event);
+        state.relay(SharedEvent::Touch(event));
+    }
+}
 
-            wl_pointer::Event::Leave { .. } => {
-                state.window().dispatch_event(WindowEvent::PointerExited);
+fn touch_event(state: &mut WindowState, event: &wl_touch::Event) {
+    // Slint has a single pointer, so only the first touch point is followed.
+    let active_touch = state.active_touch();
+    match *event {
+        wl_touch::Event::Down {
+            serial,
+            ref surface,
+            id,
+            x,
+            y,
+            ..
+        } if active_touch.is_none() && state.is_own_surface(surface) => {
+            state.set_active_touch(Some(id));
+            state.set_last_pointer_serial(serial);
+            state.set_current_pointer_position(x, y);
+            let position = *state.current_pointer_position();
+            state.dispatch_input(InputSource::Touch, WindowEvent::PointerMoved { position });
+            state.dispatch_input(
+                InputSource::Touch,
+                WindowEvent::PointerPressed {
+                    button: PointerEventButton::Left,
+                    position,
+                },
+            );
+        }
+        wl_touch::Event::Motion { id, x, y, .. } if active_touch == Some(id) => {
+            state.set_current_pointer_position(x, y);
+            let position = *state.current_pointer_position();
+            state.dispatch_input(InputSource::Touch, WindowEvent::PointerMoved { position });
+        }
+        wl_touch::Event::Up { serial, id, .. } if active_touch == Some(id) => {
+            state.set_active_touch(None);
+            state.set_last_pointer_serial(serial);
+            state.dispatch_input(
+                InputSource::Touch,
+                WindowEvent::PointerReleased {
+                    button: PointerEventButton::Left,
+                    position: *state.current_pointer_position(),
+                },
+            );
+            state.dispatch_input(InputSource::Touch, WindowEvent::PointerExited);
+        }
+        wl_touch::Event::Cancel if active_touch.is_some() => {
+            state.set_active_touch(None);
+            state.dispatch_input(InputSource::Touch, WindowEvent::PointerExited);
+        }
+        _ => {}
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for WindowState {
+    fn event(
+        state: &mut Self,
+        proxy: &WlRegistry,
+        event: <WlRegistry as Proxy>::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if interface == WlOutput::interface().name => {
+                state.handle_output_added(proxy, name, version);
             }
+            wl_registry::Event::GlobalRemove { name } => state.handle_output_removed(name),
+            _ => {}
+        }
+    }
+}
 
-            wl_pointer::Event::Button {
-                state: button_state,
-                ..
-            } => {
-                let event = match button_state {
-                    WEnum::Value(wl_pointer::ButtonState::Pressed) => WindowEvent::PointerPressed {
-                        button: PointerEventButton::Left,
-                        position: *state.current_pointer_position(),
-                    },
-                    _ => WindowEvent::PointerReleased {
-                        button: PointerEventButton::Left,
-                        position: *state.current_pointer_position(),
-                    },
-                };
-                state.window().dispatch_event(event);
+impl Dispatch<WlSeat, ()> for WindowState {
+    fn event(
+        state: &mut Self,
+        _proxy: &WlSeat,
+        event: <WlSeat as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let wl_seat::Event::Capabilities { capabilities } = event {
+            info!("Seat capabilities: {:?}", capabilities);
+            state.handle_seat_capabilities(capabilities);
+        }
+    }
+}
+
+impl Dispatch<WlKeyboard, ()> for WindowState {
+    fn event(
+        state: &mut Self,
+        _proxy: &WlKeyboard,
+        event: <WlKeyboard as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        keyboard_event(state, &event);
+        state.relay(SharedEvent::Keyboard(event));
+    }
+}
+
+fn keyboard_event(state: &mut WindowState, event: &wl_keyboard::Event) {
+    match *event {
+        wl_keyboard::Event::Keymap {
+            format,
+            ref fd,
+            size,
+        } => {
+            if format != WEnum::Value(wl_keyboard::KeymapFormat::XkbV1) {
+                info!("Ignoring keymap in unsupported format {:?}", format);
+                return;
+            }
+            // Every surface sharing the keyboard loads the keymap from its own descriptor.
+            match fd.try_clone() {
+                Ok(fd) => {
+                    if let Err(e) = state.keyboard_state_mut().load_keymap(fd, size) {
+                        error!("Failed to load keymap: {}", e);
+                    }
+                }
+                Err(e) => error!("Failed to duplicate the keymap descriptor: {}", e),
             }
+        }
+        wl_keyboard::Event::Modifiers {
+            mods_depressed,
+            mods_latched,
+            mods_locked,
+            group,
+            ..
+        } => {
+            state.keyboard_state_mut().update_modifiers(
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+            );
+        }
+        wl_keyboard::Event::Key {
+            key,
+            state: key_state,
+            ..
+        } if state.has_keyboard_focus() => {
+            let pressed = key_state == WEnum::Value(wl_keyboard::KeyState::Pressed);
+            state.handle_key(key, pressed);
+        }
+        wl_keyboard::Event::RepeatInfo { rate, delay } => {
+            state.keyboard_state_mut().set_repeat_info(rate, delay);
+        }
+        wl_keyboard::Event::Enter { ref surface, .. } if state.is_own_surface(surface) => {
+            state.handle_keyboard_enter();
+        }
+        wl_keyboard::Event::Leave { ref surface, .. } if state.is_own_surface(surface) => {
+            state.handle_keyboard_leave();
+        }
+        _ => {}
+    }
+}
+
+/// Handles an event of the outputs or input devices a mirror shares with the first surface,
+/// as it would have been dispatched had they been bound on the mirror's own queue.
+pub fn handle_shared_event(state: &mut WindowState, event: &SharedEvent) {
+    match event {
+        SharedEvent::OutputAdded(global_name, output) => {
+            state.track_output(*global_name, output.clone());
+        }
+        SharedEvent::OutputRemoved(global_name) => state.handle_output_removed(*global_name),
+        SharedEvent::Output(output, event) => output_event(state, output, event),
+        SharedEvent::DevicesRemoved(removed) => state.release_input_focus(*removed),
+        SharedEvent::Pointer(pointer, event) => pointer_event(state, pointer, event),
+        SharedEvent::Keyboard(event) => keyboard_event(state, event),
+        SharedEvent::Touch(event) => touch_event(state, event),
+    }
+}
+
+impl Dispatch<WlCallback, ()> for WindowState {
+    fn event(
+        state: &mut Self,
+        _proxy: &WlCallback,
+        event: <WlCallback as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event {
+            state.frame_done();
+        }
+    }
+}
+
+impl Dispatch<WlBuffer, ()> for WindowState {
+    fn event(
+        state: &mut Self,
+        proxy: &WlBuffer,
+        event: <WlBuffer as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if matches!(event, wl_buffer::Event::Release) {
+            state.handle_buffer_release(proxy);
+        }
+    }
+}
+
+impl Dispatch<WlSurface, ()> for WindowState {
+    fn event(
+        state: &mut Self,
+        _proxy: &WlSurface,
+        event: <WlSurface as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_surface::Event::Enter { output } => state.handle_surface_enter(&output),
+            wl_surface::Event::Leave { output } => state.handle_surface_leave(&output),
             _ => {}
         }
     }
 }
 
+impl Dispatch<WpFractionalScaleV1, ()> for WindowState {
+    fn event(
+        state: &mut Self,
+        _proxy: &WpFractionalScaleV1,
+        event: <WpFractionalScaleV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let wp_fractional_scale_v1::Event::PreferredScale { scale } = event {
+            info!("Preferred fractional scale: {}/120", scale);
+            state.set_preferred_scale(scale);
+        }
+    }
+}
+
 impl_empty_dispatch!(
-    (WlRegistry, GlobalListContents),
     (WlCompositor, ()),
-    (WlSurface, ()),
+    (WlRegion, ()),
     (ZwlrLayerShellV1, ()),
-    (WlSeat, ())
+    (WlShm, ()),
+    (WpViewporter, ()),
+    (WpViewport, ()),
+    (WpFractionalScaleManagerV1, ())
 );
@@ -1,72 +1,1955 @@
 use std::rc::Rc;
+use std::time::{Duration, Instant};
+use smithay_client_toolkit::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle, LoopSignal, RegistrationToken,
+};
 use builder::WindowStateBuilder;
-use log::info;
-use slint::{LogicalPosition, PhysicalSize, ComponentHandle};
-use slint_interpreter::ComponentInstance;
-use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::ZwlrLayerSurfaceV1;
-use wayland_client::protocol::wl_surface::WlSurface;
-use crate::rendering::femtovg_window::FemtoVGWindow;
+use configure::LayerSurfaceChanges;
+use log::{debug, error, info, warn};
+use slint::{platform::{WindowAdapter, WindowEvent}, LogicalPosition, LogicalSize, PhysicalSize, ComponentHandle};
+use slint_interpreter::{ComponentDefinition, ComponentInstance, Value};
+use smithay_client_toolkit::reexports::protocols::wp::fractional_scale::v1::client::{wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1, wp_fractional_scale_v1::WpFractionalScaleV1};
+use smithay_client_toolkit::reexports::protocols::wp::viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter};
+use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1};
+use smithay_client_toolkit::reexports::protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::{Anchor, KeyboardInteractivity};
+use wayland_client::{
+    protocol::{
+        wl_buffer::WlBuffer, wl_compositor::WlCompositor, wl_keyboard::WlKeyboard,
+        wl_output::WlOutput,
+        wl_pointer,
+        wl_region::WlRegion,
+        wl_registry::WlRegistry,
+        wl_seat,
+        wl_shm::{self, WlShm},
+        wl_surface::WlSurface,
+    },
+    Proxy, QueueHandle, WEnum,
+};
+use crate::rendering::{
+    egl_context::EGLContextBuilder, femtovg_window::FemtoVGWindow,
+    slint_platform::CustomSlintPlatform,
+};
 use crate::errors::LayerShikaError;
+#[cfg(feature = "unstable-slint-internals")]
+use crate::windowing::{color_scheme::ColorScheme, language};
+use crate::windowing::{
+    callbacks::WindowCallbacks, config::{ConfigureBehavior, HeightMode, OutputLossBehavior}, input::{FilterAction, InputEvent, InputSource}, keyboard::KeyboardState, layer_surface::LayerSurface, mirror::{SharedEvent, SharedGlobals}, outputs::{OutputMatcher, TrackedOutput}, seat::SeatDevices, shm::ShmImage,
+    snapshot::ConfigSnapshot, visibility::VisibilityTracker,
+};
 
 pub mod builder;
+pub mod configure;
 pub mod dispatches;
 
+/// Name of the Slint global the surface size is published to, see `publish_available_size`.
+const SIZE_GLOBAL: &str = "LayerShika";
+
+/// How long frame callbacks must stay stalled, or keep flowing, before visibility flips.
+const VISIBILITY_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// How long output events must stop arriving before `on_outputs_settled` fires.
+const OUTPUTS_SETTLE_DELAY: Duration = Duration::from_millis(100);
+
+/// Scroll distance of one wheel detent, in surface coordinates; what compositors commonly send
+/// as the `wl_pointer.axis` value for it.
+const WHEEL_DETENT_DISTANCE: f64 = 15.0;
+
+pub enum RenderMode {
+    /// Frames are drawn whenever the component marks itself dirty.
+    Continuous,
+    /// Frames are only drawn when explicitly requested; `pending` holds such a request.
+    Static { pending: bool },
+}
+
+/// Whether a positive exclusive zone is currently reserved or given up, see
+/// [`WindowState::set_fullscreen_on_output`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ZoneReservation {
+    Reserved,
+    YieldedToFullscreen,
+}
+
+/// Whether the pointer is over this surface. All pointers of a client report on all its
+/// surfaces, so events for other surfaces of the process are told apart by it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PointerFocus {
+    Elsewhere,
+    Surface,
+}
+
+/// Whether the surface's output is presenting it, as tracked for `on_output_enabled`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputActivity {
+    Active,
+    /// The surface left its output, or frame callbacks stalled; rendering is suspended.
+    Inactive,
+}
+
+/// The surface's `wp_fractional_scale_v1` object and the scale the compositor prefers for it,
+/// which replaces the integer output scale when following the output scale.
+struct FractionalScale {
+    manager: WpFractionalScaleManagerV1,
+    object: WpFractionalScaleV1,
+    /// `None` until the first `preferred_scale` event.
+    preferred: Option<f32>,
+}
+
+/// Pointer input received since the last `wl_pointer.frame`.
+#[derive(Default)]
+struct PointerFrame {
+    events: Vec<WindowEvent>,
+    horizontal: AxisScroll,
+    vertical: AxisScroll,
+}
+
+/// Scroll along one axis received since the last `wl_pointer.frame`.
+#[derive(Default)]
+struct AxisScroll {
+    /// Distance from `wl_pointer.axis`, in surface coordinates.
+    distance: f64,
+    /// Wheel rotation in 1/120 of a detent, from `axis_value120` or `axis_discrete`.
+    value120: Option<i32>,
+}
+
+impl AxisScroll {
+    /// The distance to scroll by: wheel detents when the wheel reported them, so every detent
+    /// scrolls the same, otherwise the continuous distance, e.g. from a touchpad.
+    fn distance(&self) -> f64 {
+        self.value120.map_or(self.distance, |value120| {
+            f64::from(value120) / 120.0 * WHEEL_DETENT_DISTANCE
+        })
+    }
+}
+
+/// Part of the surface declared opaque to the compositor.
+enum OpaqueRegion {
+    Unset,
+    Rects(Vec<(i32, i32, i32, i32)>),
+    /// Follows the surface size, see `with_opaque`.
+    WholeSurface,
+}
+
+/// Whether the current surface has been configured yet, see `commit_placeholder`, destroyed
+/// until its output returns, or closed for good, which ends `run`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SurfaceMapping {
+    AwaitingConfigure,
+    Configured,
+    Detached,
+    Closed,
+}
+
+/// Progress of the opt-in check of the first configure against the requested size.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ConfigureDiagnostics {
+    Off,
+    Pending,
+    Done,
+}
+
+/// Countdown after which an auto-closing surface closes itself.
+struct AutoClose {
+    timeout: Duration,
+    /// Pending countdown, `None` before the first configure and while paused.
+    timer: Option<RegistrationToken>,
+    mapped: bool,
+}
+
 pub struct WindowState {
     component_instance: ComponentInstance,
+    component_definition: ComponentDefinition,
+    /// Property values right after the component was created, to tell which ones were changed
+    /// at runtime when it is replaced.
+    property_defaults: Vec<(String, Value)>,
+    initial_properties: Vec<(String, Value)>,
+    /// Function or callback that moves focus into the component, see `with_initial_focus`.
+    initial_focus: Option<String>,
+    compositor: Rc<WlCompositor>,
+    shm: WlShm,
+    queue_handle: QueueHandle<Self>,
     surface: Rc<WlSurface>,
-    layer_surface: Rc<ZwlrLayerSurfaceV1>,
+    layer_surface: Rc<LayerSurface>,
+    layer_shell: ZwlrLayerShellV1,
+    layer: Layer,
+    output: WlOutput,
+    outputs: Vec<TrackedOutput>,
+    /// Accepts the outputs the surface may move or return to, from `with_output_matcher`.
+    output_matcher: Option<OutputMatcher>,
+    shared_globals: SharedGlobals,
+    viewporter: Option<WpViewporter>,
+    viewport: Option<WpViewport>,
+    viewport_destination: Option<(i32, i32)>,
     size: PhysicalSize,
+    /// Buffer and logical size last reported to `on_resized`.
+    reported_size: Option<(PhysicalSize, LogicalSize)>,
     output_size: PhysicalSize,
+    output_refresh_mhz: Option<u32>,
+    output_scale: i32,
+    output_name: Option<String>,
+    /// Names of outputs rendering is suspended for, see `pause_output`.
+    paused_outputs: Vec<String>,
+    output_activity: OutputActivity,
+    /// Outputs the surface has entered and not left, in the order it entered them.
+    entered_outputs: Vec<WlOutput>,
+    /// Pending `on_outputs_settled` notification, restarted by every output event.
+    outputs_settle_timer: Option<RegistrationToken>,
+    scale_mismatch_reported: bool,
+    /// Output scale last reported to `on_scale_conflict`.
+    scale_conflict_reported: Option<i32>,
     window: Rc<FemtoVGWindow>,
+    /// EGL context to attach before the first frame, see `defer_renderer_creation`.
+    pending_context: Option<EGLContextBuilder>,
+    /// Template for the context of a surface re-created on another output.
+    context_builder: EGLContextBuilder,
+    seat_devices: SeatDevices,
+    keyboard_state: KeyboardState,
+    /// Held key that is repeating, with the timer re-sending it.
+    key_repeat: Option<(u32, RegistrationToken)>,
     current_pointer_position: LogicalPosition,
+    pointer_frame: PointerFrame,
+    pointer_focus: PointerFocus,
+    input_region: Option<Vec<(i32, i32, i32, i32)>>,
+    opaque_region: OpaqueRegion,
+    last_pointer_serial: Option<u32>,
+    /// Id of the touch point currently driving the Slint pointer.
+    active_touch: Option<i32>,
     scale_factor: f32,
+    render_scale: f32,
+    /// Integer buffer scale following the output's, `None` unless `follow_output_scale` is set.
+    buffer_scale: Option<i32>,
+    fractional_scale: Option<FractionalScale>,
     height: u32,
+    height_mode: HeightMode,
+    /// Fixed width from `with_width`, `None` to span the output.
+    width: Option<u32>,
     exclusive_zone: i32,
+    max_exclusive_fraction: Option<f32>,
+    zone_reservation: ZoneReservation,
+    configure_diagnostics: ConfigureDiagnostics,
+    mapping: SurfaceMapping,
+    configure_behavior: ConfigureBehavior,
+    output_loss_behavior: OutputLossBehavior,
+    anchor: Anchor,
+    margin: (i32, i32, i32, i32),
+    keyboard_interactivity: KeyboardInteractivity,
+    hot_edge: Option<u32>,
+    hot_edge_revealed: bool,
+    namespace: String,
+    blanked: bool,
+    render_mode: RenderMode,
+    image: Option<ShmImage>,
+    retired_images: Vec<ShmImage>,
+    /// Transparent buffers the surface was mapped or blanked with, until the compositor
+    /// releases them.
+    placeholders: Vec<ShmImage>,
+    visibility: VisibilityTracker,
+    loop_handle: LoopHandle<'static, Self>,
+    loop_signal: LoopSignal,
+    auto_close: Option<AutoClose>,
+    callbacks: WindowCallbacks,
 }
 
 impl WindowState {
+    #[allow(clippy::too_many_lines)]
     pub fn new(builder: WindowStateBuilder) -> Result<Self, LayerShikaError> {
         let component_definition = builder.component_definition.ok_or_else(|| {
             LayerShikaError::InvalidInput("Component definition is required".into())
         })?;
-        let component_instance = component_definition
-            .create()
-            .map_err(|e| LayerShikaError::SlintComponentCreation(e.to_string()))?;
-        component_instance
-            .show()
-            .map_err(|e| LayerShikaError::SlintComponentCreation(e.to_string()))?;
+        let window = builder
+            .window
+            .ok_or_else(|| LayerShikaError::InvalidInput("Window is required".into()))?;
+        let component_instance = Self::instantiate(
+            &component_definition,
+            &builder.properties,
+            builder.initial_focus.as_deref(),
+            &window,
+        )?;
+        let property_defaults = Self::property_values(&component_definition, &component_instance);
         Ok(Self {
             component_instance,
+            component_definition,
+            property_defaults,
+            initial_properties: builder.properties,
+            initial_focus: builder.initial_focus,
+            compositor: builder
+                .compositor
+                .ok_or_else(|| LayerShikaError::InvalidInput("Compositor is required".into()))?,
+            shm: builder
+                .shm
+                .ok_or_else(|| LayerShikaError::InvalidInput("Shm is required".into()))?,
+            queue_handle: builder
+                .queue_handle
+                .ok_or_else(|| LayerShikaError::InvalidInput("Queue handle is required".into()))?,
             surface: builder
                 .surface
                 .ok_or_else(|| LayerShikaError::InvalidInput("Surface is required".into()))?,
             layer_surface: builder
                 .layer_surface
                 .ok_or_else(|| LayerShikaError::InvalidInput("Layer surface is required".into()))?,
+            layer_shell: builder
+                .layer_shell
+                .ok_or_else(|| LayerShikaError::InvalidInput("Layer shell is required".into()))?,
+            layer: builder.layer,
+            output: builder
+                .output
+                .ok_or_else(|| LayerShikaError::InvalidInput("Output is required".into()))?,
+            outputs: builder.outputs,
+            output_matcher: builder.output_matcher,
+            shared_globals: builder.shared_globals,
+            viewporter: builder.viewporter,
+            viewport: builder.viewport,
+            viewport_destination: None,
             size: builder.size.unwrap_or_default(),
+            reported_size: None,
             output_size: builder.output_size.unwrap_or_default(),
-            window: builder
-                .window
-                .ok_or_else(|| LayerShikaError::InvalidInput("Window is required".into()))?,
+            output_refresh_mhz: None,
+            output_scale: 1,
+            output_name: None,
+            paused_outputs: Vec::new(),
+            output_activity: OutputActivity::Active,
+            entered_outputs: Vec::new(),
+            outputs_settle_timer: None,
+            scale_mismatch_reported: false,
+            scale_conflict_reported: None,
+            window,
+            pending_context: builder.pending_context,
+            context_builder: builder.context_builder.ok_or_else(|| {
+                LayerShikaError::InvalidInput("Context builder is required".into())
+            })?,
+            seat_devices: builder
+                .seat_devices
+                .ok_or_else(|| LayerShikaError::InvalidInput("Seat is required".into()))?,
+            keyboard_state: KeyboardState::new(),
+            key_repeat: None,
             current_pointer_position: LogicalPosition::default(),
+            pointer_frame: PointerFrame::default(),
+            pointer_focus: PointerFocus::Elsewhere,
+            input_region: None,
+            opaque_region: if builder.opaque {
+                OpaqueRegion::WholeSurface
+            } else {
+                OpaqueRegion::Unset
+            },
+            last_pointer_serial: None,
+            active_touch: None,
             scale_factor: builder.scale_factor,
+            render_scale: builder.render_scale,
+            buffer_scale: builder.follow_output_scale.then_some(1),
+            fractional_scale: builder
+                .fractional_scale
+                .map(|(manager, object)| FractionalScale {
+                    manager,
+                    object,
+                    preferred: None,
+                }),
             height: builder.height,
+            width: builder.width,
             exclusive_zone: builder.exclusive_zone,
+            max_exclusive_fraction: builder.max_exclusive_fraction,
+            zone_reservation: ZoneReservation::Reserved,
+            configure_diagnostics: if builder.configure_diagnostics {
+                ConfigureDiagnostics::Pending
+            } else {
+                ConfigureDiagnostics::Off
+            },
+            mapping: SurfaceMapping::AwaitingConfigure,
+            configure_behavior: builder.configure_behavior,
+            output_loss_behavior: builder.output_loss_behavior,
+            height_mode: builder.height_mode,
+            anchor: builder.anchor,
+            margin: builder.margin,
+            keyboard_interactivity: builder.keyboard_interactivity,
+            hot_edge: builder.hot_edge,
+            hot_edge_revealed: false,
+            namespace: builder.namespace,
+            blanked: false,
+            render_mode: RenderMode::Continuous,
+            image: None,
+            retired_images: Vec::new(),
+            placeholders: Vec::new(),
+            visibility: VisibilityTracker::new(VISIBILITY_DEBOUNCE),
+            loop_handle: builder
+                .loop_handle
+                .ok_or_else(|| LayerShikaError::InvalidInput("Loop handle is required".into()))?,
+            loop_signal: builder
+                .loop_signal
+                .ok_or_else(|| LayerShikaError::InvalidInput("Loop signal is required".into()))?,
+            auto_close: builder.auto_close.map(|timeout| AutoClose {
+                timeout,
+                timer: None,
+                mapped: false,
+            }),
+            callbacks: builder.callbacks,
         })
     }
 
+    /// Creates a component in `window`, applies `properties`, shows it and invokes the
+    /// `initial_focus` callback if any.
+    pub fn instantiate<W: WindowAdapter + 'static>(
+        definition: &ComponentDefinition,
+        properties: &[(String, Value)],
+        initial_focus: Option<&str>,
+        window: &Rc<W>,
+    ) -> Result<ComponentInstance, LayerShikaError> {
+        let component_instance = CustomSlintPlatform::create_in(window, || definition.create())
+            .map_err(|e| LayerShikaError::SlintComponentCreation(e.to_string()))?;
+        for (name, value) in properties {
+            component_instance
+                .set_property(name, value.clone())
+                .map_err(|e| {
+                    LayerShikaError::SlintComponentCreation(format!(
+                        "Failed to set property `{name}`: {e}"
+                    ))
+                })?;
+        }
+        component_instance
+            .show()
+            .map_err(|e| LayerShikaError::SlintComponentCreation(e.to_string()))?;
+        if let Some(name) = initial_focus {
+            component_instance.invoke(name, &[]).map_err(|e| {
+                LayerShikaError::SlintComponentCreation(format!(
+                    "Failed to invoke initial focus `{name}`: {e}"
+                ))
+            })?;
+        }
+        Ok(component_instance)
+    }
+
+    /// Replaces the component with a fresh instance of the same definition, with the properties
+    /// from `with_property` applied again, discarding all UI state of the old one.
+    pub fn reset_component(&mut self) -> Result<(), LayerShikaError> {
+        info!("Re-creating component instance");
+        self.component_instance
+            .hide()
+            .map_err(|e| LayerShikaError::SlintComponentCreation(e.to_string()))?;
+        self.component_instance = Self::instantiate(
+            &self.component_definition,
+            &self.initial_properties,
+            self.initial_focus.as_deref(),
+            &self.window,
+        )?;
+        self.property_defaults =
+            Self::property_values(&self.component_definition, &self.component_instance);
+        self.publish_available_size(self.size.to_logical(self.scale_factor));
+        self.render_once();
+        Ok(())
+    }
+
+    /// Swaps in a component built from a new definition, e.g. after its markup changed on disk.
+    /// Property values changed at runtime, e.g. pushed in by the application, are carried over
+    /// where the new definition still declares them; the others come from the new markup. The
+    /// old component stays if the new one can't be created.
+    pub fn replace_component(
+        &mut self,
+        definition: ComponentDefinition,
+    ) -> Result<(), LayerShikaError> {
+        info!("Replacing component instance");
+        let changed = self.changed_properties();
+        self.component_instance
+            .hide()
+            .map_err(|e| LayerShikaError::SlintComponentCreation(e.to_string()))?;
+        let component_instance = match Self::instantiate(
+            &definition,
+            &self.initial_properties,
+            self.initial_focus.as_deref(),
+            &self.window,
+        ) {
+            Ok(component_instance) => component_instance,
+            Err(e) => {
+                self.component_instance
+                    .show()
+                    .map_err(|e| LayerShikaError::SlintComponentCreation(e.to_string()))?;
+                return Err(e);
+            }
+        };
+        let property_defaults = Self::property_values(&definition, &component_instance);
+        for (name, value) in changed {
+            if let Err(e) = component_instance.set_property(&name, value) {
+                debug!("Not carrying over property `{name}`: {e}");
+            }
+        }
+        self.component_instance = component_instance;
+        self.component_definition = definition;
+        self.property_defaults = property_defaults;
+        self.publish_available_size(self.size.to_logical(self.scale_factor));
+        self.render_once();
+        self.callbacks.component_reloaded(&self.component_instance);
+        Ok(())
+    }
+
+    /// Property values changed at runtime, e.g. pushed in by the application or by the
+    /// component itself.
+    pub fn changed_properties(&self) -> Vec<(String, Value)> {
+        Self::property_values(&self.component_definition, &self.component_instance)
+            .into_iter()
+            .filter(|property| !self.property_defaults.contains(property))
+            .collect()
+    }
+
+    fn property_values(
+        definition: &ComponentDefinition,
+        component_instance: &ComponentInstance,
+    ) -> Vec<(String, Value)> {
+        definition
+            .properties()
+            .filter_map(|(name, _)| {
+                let value = component_instance.get_property(&name).ok()?;
+                Some((name, value))
+            })
+            .collect()
+    }
+
+    /// Resizes the surface from user code, e.g. to expand a drawer: the content is laid out at
+    /// the new size right away and the size is requested from the compositor in the same commit.
+    /// Stops following the preferred height set up with `with_auto_height`.
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        self.width = Some(width);
+        self.height = height;
+        self.height_mode = HeightMode::Fixed;
+        // A detached surface picks the size up when it is re-created.
+        if self.mapping != SurfaceMapping::Detached {
+            self.update_size(self.surface_width(), height);
+        }
+    }
+
     pub fn update_size(&mut self, width: u32, height: u32) {
+        let height = self.clamp_to_output_height(height);
         let new_size = PhysicalSize::new(width, height);
-        info!("Updating window size to {}x{}", width, height);
-        self.window.set_size(slint::WindowSize::Physical(new_size));
-        self.window.set_scale_factor(self.scale_factor);
+        let buffer_size = self.buffer_size(new_size);
+        info!(
+            "Updating window size to {}x{}",
+            buffer_size.width, buffer_size.height
+        );
+        self.window
+            .set_scale_factor(self.scale_factor * self.buffer_density());
+        self.window
+            .set_size(slint::WindowSize::Physical(buffer_size));
+        if let Some(viewport) = &self.viewport {
+            let (width, height) = self.viewport_destination.unwrap_or_else(|| {
+                (
+                    i32::try_from(width).unwrap_or(i32::MAX),
+                    i32::try_from(height).unwrap_or(i32::MAX),
+                )
+            });
+            viewport.set_destination(width, height);
+        }
+
+        // The compositor may have granted less than requested, e.g. because another client's
+        // exclusive zone shrank the usable area, so keep asking for the configured size.
+        let (requested_width, requested_height) = self.requested_size();
+        info!("Requesting layer surface size {requested_width}x{requested_height}");
+        self.layer_surface
+            .set_size(requested_width, requested_height);
+        if self.configure_behavior == ConfigureBehavior::Reapply {
+            self.layer_surface
+                .set_exclusive_zone(self.effective_exclusive_zone());
+            if self.hot_edge.is_some() {
+                self.apply_margin();
+            }
+        }
+
+        self.size = new_size;
+        if !matches!(self.opaque_region, OpaqueRegion::Unset) {
+            self.apply_opaque_region();
+        }
+        self.surface.commit();
+
+        let logical_size = new_size.to_logical(self.scale_factor);
+        self.publish_available_size(logical_size);
+        let reported = Some((buffer_size, logical_size));
+        if self.reported_size != reported {
+            self.reported_size = reported;
+            self.callbacks.resized(buffer_size, logical_size);
+        }
+    }
+
+    /// Pushes the logical surface size into the optional `LayerShika` global, which components
+    /// can declare to bind their layout to the actual surface dimensions:
+    ///
+    /// ```slint
+    /// export global LayerShika {
+    ///     in property <length> available-width;
+    ///     in property <length> available-height;
+    /// }
+    /// ```
+    ///
+    /// The global must be exported from the main file. Components without it are left alone.
+    fn publish_available_size(&self, size: LogicalSize) {
+        for (property, value) in [
+            ("available-width", size.width),
+            ("available-height", size.height),
+        ] {
+            if let Err(e) = self.component_instance.set_global_property(
+                SIZE_GLOBAL,
+                property,
+                Value::Number(f64::from(value)),
+            ) {
+                debug!("Not publishing {SIZE_GLOBAL}.{property}: {e}");
+            }
+        }
+    }
+
+    /// Limits `height` to the output height once it is known, since compositors clamp or reject
+    /// taller surfaces in ways that are hard to tell apart from layout bugs.
+    fn clamp_to_output_height(&self, height: u32) -> u32 {
+        let output_height = self.surface_output_size().height;
+        if output_height == 0 || height <= output_height {
+            return height;
+        }
+        warn!(
+            "Requested height {} exceeds the output height {}, clamping",
+            height, output_height
+        );
+        output_height
+    }
+
+    /// Size of the buffer rendered for a surface of `surface_size`, scaled by the buffer density.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn buffer_size(&self, surface_size: PhysicalSize) -> PhysicalSize {
+        let density = self.buffer_density();
+        if (density - 1.0).abs() <= f32::EPSILON {
+            return surface_size;
+        }
+        let scale = |extent: u32| ((extent as f32 * density).round() as u32).max(1);
+        PhysicalSize::new(scale(surface_size.width), scale(surface_size.height))
+    }
+
+    /// Buffer pixels rendered per surface pixel: the render scale times the output scale being
+    /// followed, fractional if the compositor prefers one.
+    fn buffer_density(&self) -> f32 {
+        self.render_scale * self.followed_scale()
+    }
+
+    /// Scale of the output the surface is on as far as the buffer follows it, `1.0` when not
+    /// following the output scale.
+    #[allow(clippy::cast_precision_loss)]
+    fn followed_scale(&self) -> f32 {
+        self.fractional_scale
+            .as_ref()
+            .and_then(|fractional| fractional.preferred)
+            .unwrap_or_else(|| self.buffer_scale.unwrap_or(1).max(1) as f32)
+    }
+
+    /// The output size in surface coordinates, i.e. divided by the scale being followed.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    fn surface_output_size(&self) -> PhysicalSize {
+        let scale = self.followed_scale();
+        let unscale = |extent: u32| (extent as f32 / scale).round() as u32;
+        PhysicalSize::new(
+            unscale(self.output_size.width),
+            unscale(self.output_size.height),
+        )
+    }
+
+    /// Width from `with_width`, unless the surface is stretched between the left and right edges.
+    fn requested_width(&self) -> Option<u32> {
+        self.width
+            .filter(|_| !self.anchor.contains(Anchor::Left | Anchor::Right))
+    }
+
+    /// Size to ask the compositor for: nothing when anchored to all edges, so it fills the output.
+    fn requested_size(&self) -> (u32, u32) {
+        if self.anchor.is_all() {
+            (0, 0)
+        } else {
+            (
+                self.surface_width(),
+                self.clamp_to_output_height(self.height),
+            )
+        }
+    }
+
+    /// Width of the surface outside of fullscreen: the requested one, or the output width.
+    fn surface_width(&self) -> u32 {
+        self.requested_width()
+            .unwrap_or_else(|| self.surface_output_size().width)
+    }
+
+    /// Picks up a changed output scale when following it: the buffer is re-created at the new
+    /// density and the content re-rendered, keeping the surface size.
+    pub fn apply_output_scale(&mut self) {
+        let output_scale = self.output_scale.max(1);
+        if self.fractional_scale.is_some()
+            || self.buffer_scale.is_none_or(|scale| scale == output_scale)
+        {
+            return;
+        }
+        info!("Output scale changed to {output_scale}, re-rendering");
+        if self.viewport.is_none() {
+            if self.surface.version() < 3 {
+                warn!("wl_surface.set_buffer_scale is not supported, keeping buffer scale 1");
+                return;
+            }
+            // With a viewport, the destination size already maps the buffer to the surface.
+            self.surface.set_buffer_scale(output_scale);
+        }
+        self.buffer_scale = Some(output_scale);
+        self.rerender_at_new_density();
+    }
+
+    /// Takes the scale from `wp_fractional_scale_v1.preferred_scale`, in 120ths, as the buffer
+    /// density. The viewport maps the fractionally sized buffer back to the surface size.
+    pub fn set_preferred_scale(&mut self, scale: u32) {
+        let Some(fractional) = self.fractional_scale.as_mut() else {
+            return;
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let scale = scale as f32 / 120.0;
+        if fractional.preferred == Some(scale) {
+            return;
+        }
+        info!("Preferred fractional scale changed to {scale}, re-rendering");
+        fractional.preferred = Some(scale);
+        self.rerender_at_new_density();
+    }
+
+    fn rerender_at_new_density(&mut self) {
+        if self.size.width > 0 && self.size.height > 0 {
+            self.update_size(self.size.width, self.size.height);
+        }
+        self.window.request_redraw();
+        if self.is_static_rendering() {
+            self.render_once();
+        }
+    }
+
+    /// Crops the rendered buffer to the given rectangle, in buffer coordinates, before it is
+    /// scaled to the viewport destination. Passing `-1.0` for all values shows the whole buffer.
+    pub fn set_viewport_source(
+        &self,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    ) -> Result<(), LayerShikaError> {
+        let viewport = self.viewport()?;
+        viewport.set_source(x, y, width, height);
+        self.surface.commit();
+        Ok(())
+    }
+
+    /// Scales the (possibly cropped) buffer to `width`x`height` surface pixels, overriding the
+    /// default of matching the configured surface size, including across later resizes.
+    pub fn set_viewport_destination(
+        &mut self,
+        width: i32,
+        height: i32,
+    ) -> Result<(), LayerShikaError> {
+        self.viewport()?.set_destination(width, height);
+        self.viewport_destination = Some((width, height));
+        self.surface.commit();
+        Ok(())
+    }
+
+    fn viewport(&self) -> Result<&WpViewport, LayerShikaError> {
+        self.viewport.as_ref().ok_or_else(|| {
+            LayerShikaError::UnsupportedProtocol("wp_viewporter is not available".into())
+        })
+    }
 
-        info!("Updating layer surface size to {}x{}", width, height);
+    /// Re-sends anchor, margin, exclusive zone, keyboard interactivity and size to the
+    /// compositor and commits, e.g. after a reconnect or when the compositor signals a reset.
+    pub fn reapply_config(&self) {
+        info!("Re-applying layer surface configuration");
+        self.layer_surface.set_anchor(self.anchor);
+        self.apply_margin();
+        self.layer_surface
+            .set_exclusive_zone(self.effective_exclusive_zone());
+        self.layer_surface
+            .set_keyboard_interactivity(self.keyboard_interactivity);
+        let (width, height) = self.requested_size();
         self.layer_surface.set_size(width, height);
-        self.layer_surface.set_exclusive_zone(self.exclusive_zone);
+        self.surface.commit();
+    }
 
+    /// Applies all layer surface changes made in `changes` with a single commit, so the
+    /// compositor sees one consistent reconfiguration instead of a series of intermediate ones.
+    pub fn configure(&mut self, changes: impl FnOnce(&mut LayerSurfaceChanges)) {
+        let mut pending = LayerSurfaceChanges::default();
+        changes(&mut pending);
+
+        if let Some(anchor) = pending.anchor {
+            self.anchor = anchor;
+            self.layer_surface.set_anchor(anchor);
+        }
+        if let Some(margin) = pending.margin {
+            self.margin = margin;
+        }
+        if pending.margin.is_some() || pending.anchor.is_some() {
+            // The hot-edge offset depends on both the margin and the anchored edge.
+            self.apply_margin();
+        }
+        if let Some(exclusive_zone) = pending.exclusive_zone {
+            self.exclusive_zone = exclusive_zone;
+        }
+        if pending.exclusive_zone.is_some()
+            || (pending.anchor.is_some() && self.max_exclusive_fraction.is_some())
+        {
+            // The cap depends on the output extent along the anchored edge.
+            self.layer_surface
+                .set_exclusive_zone(self.effective_exclusive_zone());
+        }
+        if let Some(keyboard_interactivity) = pending.keyboard_interactivity {
+            self.keyboard_interactivity = keyboard_interactivity;
+            self.layer_surface
+                .set_keyboard_interactivity(keyboard_interactivity);
+        }
+        if let Some(height) = pending.height {
+            self.height = height;
+        }
+        if pending.height.is_some() || pending.anchor.is_some() {
+            // Which dimensions are left to the compositor depends on the anchored edges.
+            let (width, height) = self.requested_size();
+            self.layer_surface.set_size(width, height);
+        }
+
+        info!("Committing layer surface configuration");
         self.surface.commit();
-        self.size = new_size;
+    }
+
+    /// Requests the component's preferred height with `with_auto_height` once it changed,
+    /// together with a positive exclusive zone of the same extent. A zone of 0 or -1 is left
+    /// alone. The window keeps its size until the compositor's configure grants the new one.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn follow_preferred_height(&mut self) {
+        if self.height_mode == HeightMode::Fixed || self.anchor.is_all() {
+            return;
+        }
+        let preferred = self.window.preferred_size().height;
+        if preferred <= 0. {
+            return;
+        }
+        let height = (preferred * self.scale_factor).ceil() as u32;
+        if height == self.height {
+            return;
+        }
+        info!("Following preferred height: {} -> {}", self.height, height);
+        let reserve = self.exclusive_zone > 0;
+        self.configure(|c| {
+            c.height(height);
+            if reserve {
+                c.exclusive_zone(i32::try_from(height).unwrap_or(i32::MAX));
+            }
+        });
+    }
+
+    /// Moves the surface to other edges, e.g. a bar from the top to the bottom of the output,
+    /// re-requesting the size and exclusive zone for the new edges in the same commit.
+    pub fn set_anchor(&mut self, anchor: Anchor) {
+        self.configure(|c| {
+            c.anchor(anchor);
+        });
+    }
+
+    pub fn set_margin(&mut self, top: i32, right: i32, bottom: i32, left: i32) {
+        self.configure(|c| {
+            c.margin(top, right, bottom, left);
+        });
+    }
+
+    /// Grows or shrinks the reserved area, e.g. while a bar auto-hides. Later resizes keep using
+    /// this value.
+    pub fn set_exclusive_zone(&mut self, exclusive_zone: i32) {
+        self.configure(|c| {
+            c.exclusive_zone(exclusive_zone);
+        });
+    }
+
+    /// Switches keyboard interactivity, e.g. for a launcher that only grabs the keyboard while
+    /// open. The mode is kept for later re-applies of the configuration.
+    pub fn set_keyboard_interactivity(&mut self, keyboard_interactivity: KeyboardInteractivity) {
+        self.configure(|c| {
+            c.keyboard_interactivity(keyboard_interactivity);
+        });
+    }
+
+    /// Applies the runtime-changeable parts of `snapshot` with a single commit.
+    pub fn apply_snapshot(&mut self, snapshot: &ConfigSnapshot) {
+        let (top, right, bottom, left) = snapshot.margin;
+        self.configure(|c| {
+            c.anchor(snapshot.anchor)
+                .margin(top, right, bottom, left)
+                .height(snapshot.height)
+                .exclusive_zone(snapshot.exclusive_zone)
+                .keyboard_interactivity(snapshot.keyboard_interactivity);
+        });
+    }
+
+    /// Gives up a positive exclusive zone while a toplevel is fullscreen on the surface's output,
+    /// and reserves it again once `fullscreen` is `false`. Layer-shell doesn't report fullscreen
+    /// toplevels, so this has to be driven from e.g. `ext_foreign_toplevel_list_v1` or
+    /// `zwlr_foreign_toplevel_manager_v1` handled by the application.
+    pub fn set_fullscreen_on_output(&mut self, fullscreen: bool) {
+        let reservation = if fullscreen {
+            ZoneReservation::YieldedToFullscreen
+        } else {
+            ZoneReservation::Reserved
+        };
+        if self.zone_reservation == reservation {
+            return;
+        }
+        info!(
+            "{} exclusive zone for a fullscreen toplevel",
+            if fullscreen { "Yielding" } else { "Restoring" }
+        );
+        self.zone_reservation = reservation;
+        self.layer_surface
+            .set_exclusive_zone(self.effective_exclusive_zone());
+        self.surface.commit();
+    }
+
+    /// Slides a hot-edge surface fully on screen. Does nothing without `with_hot_edge`.
+    pub fn reveal_hot_edge(&mut self) {
+        if self.hot_edge.is_some() && !self.hot_edge_revealed {
+            info!("Revealing hot-edge surface");
+            self.hot_edge_revealed = true;
+            self.apply_margin();
+            self.surface.commit();
+        }
+    }
+
+    /// Pushes a hot-edge surface back off-screen, leaving only the hot strip mapped.
+    pub fn conceal_hot_edge(&mut self) {
+        if self.hot_edge.is_some() && self.hot_edge_revealed {
+            info!("Concealing hot-edge surface");
+            self.hot_edge_revealed = false;
+            self.apply_margin();
+            self.surface.commit();
+        }
+    }
+
+    /// The exclusive zone sent to the compositor: the configured one, capped by
+    /// `with_max_exclusive_fraction` once the output size is known.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn effective_exclusive_zone(&self) -> i32 {
+        if self.zone_reservation == ZoneReservation::YieldedToFullscreen && self.exclusive_zone > 0
+        {
+            return 0;
+        }
+        let Some(fraction) = self.max_exclusive_fraction else {
+            return self.exclusive_zone;
+        };
+        let anchor = self.anchor;
+        let vertical_bar = (anchor.contains(Anchor::Left) != anchor.contains(Anchor::Right))
+            && (anchor.contains(Anchor::Top) == anchor.contains(Anchor::Bottom));
+        let output_size = self.surface_output_size();
+        let extent = if vertical_bar {
+            output_size.width
+        } else {
+            output_size.height
+        };
+        if self.exclusive_zone <= 0 || extent == 0 {
+            return self.exclusive_zone;
+        }
+        let max = (extent as f32 * fraction).floor() as i32;
+        if self.exclusive_zone > max {
+            debug!(
+                "Capping exclusive zone {} at {} ({} of {})",
+                self.exclusive_zone, max, fraction, extent
+            );
+            return max;
+        }
+        self.exclusive_zone
+    }
+
+    fn apply_margin(&self) {
+        let (top, right, bottom, left) = self.effective_margin();
+        self.layer_surface.set_margin(top, right, bottom, left);
+    }
+
+    fn effective_margin(&self) -> (i32, i32, i32, i32) {
+        let (mut top, mut right, mut bottom, mut left) = self.margin;
+        let Some(thickness) = self.hot_edge else {
+            return self.margin;
+        };
+        if self.hot_edge_revealed {
+            return self.margin;
+        }
+
+        let hidden = |extent: u32| -i32::try_from(extent.saturating_sub(thickness)).unwrap_or(0);
+        let anchor = self.anchor;
+        if anchor.contains(Anchor::Top) && !anchor.contains(Anchor::Bottom) {
+            top += hidden(self.height);
+        } else if anchor.contains(Anchor::Bottom) && !anchor.contains(Anchor::Top) {
+            bottom += hidden(self.height);
+        } else if anchor.contains(Anchor::Left) && !anchor.contains(Anchor::Right) {
+            left += hidden(self.size.width);
+        } else if anchor.contains(Anchor::Right) && !anchor.contains(Anchor::Left) {
+            right += hidden(self.size.width);
+        } else {
+            warn!("Hot edge requires the surface to be anchored to a single edge, ignoring it");
+        }
+        (top, right, bottom, left)
+    }
+
+    /// Restricts pointer and touch input to the given `(x, y, width, height)` rectangles in
+    /// surface coordinates; everywhere else input passes through to the surfaces below.
+    pub fn set_input_region_only(&mut self, rects: &[(i32, i32, i32, i32)]) {
+        let region = self.create_region(rects);
+        self.surface.set_input_region(Some(&region));
+        region.destroy();
+        self.surface.commit();
+        self.input_region = Some(rects.to_vec());
+    }
+
+    /// Like [`Self::set_input_region_only`] for `Some`, where an empty slice makes the whole
+    /// surface click-through; `None` makes the whole surface accept input again.
+    pub fn set_input_region(&mut self, rects: Option<&[(i32, i32, i32, i32)]>) {
+        let Some(rects) = rects else {
+            self.surface.set_input_region(None);
+            self.surface.commit();
+            self.input_region = None;
+            return;
+        };
+        self.set_input_region_only(rects);
+    }
+
+    /// Declares the given `(x, y, width, height)` rectangles, in surface coordinates, as fully
+    /// opaque; an empty slice makes the whole surface translucent again. Independent of the input
+    /// region. The compositor only uses this to skip drawing what lies underneath, so declaring
+    /// pixels opaque that are not (e.g. rounded corners) leaves garbage behind them.
+    pub fn set_opaque_region(&mut self, rects: &[(i32, i32, i32, i32)]) {
+        self.opaque_region = if rects.is_empty() {
+            OpaqueRegion::Unset
+        } else {
+            OpaqueRegion::Rects(rects.to_vec())
+        };
+        self.apply_opaque_region();
+        self.surface.commit();
+    }
+
+    /// Marks a single rectangle opaque, e.g. the body of a panel whose corners are translucent.
+    /// See [`Self::set_opaque_region`].
+    pub fn set_opaque_region_rect(&mut self, rect: (i32, i32, i32, i32)) {
+        self.set_opaque_region(&[rect]);
+    }
+
+    /// Declares the whole surface opaque, keeping the region in sync with its size, or makes
+    /// it translucent again.
+    pub fn set_opaque(&mut self, opaque: bool) {
+        self.opaque_region = if opaque {
+            OpaqueRegion::WholeSurface
+        } else {
+            OpaqueRegion::Unset
+        };
+        self.apply_opaque_region();
+        self.surface.commit();
+    }
+
+    /// Sets the opaque region on the surface, to be applied with the next commit.
+    fn apply_opaque_region(&self) {
+        let whole_surface;
+        let rects = match &self.opaque_region {
+            OpaqueRegion::Unset => {
+                self.surface.set_opaque_region(None);
+                return;
+            }
+            OpaqueRegion::Rects(rects) => rects.as_slice(),
+            OpaqueRegion::WholeSurface => {
+                whole_surface = [(
+                    0,
+                    0,
+                    i32::try_from(self.size.width).unwrap_or(i32::MAX),
+                    i32::try_from(self.size.height).unwrap_or(i32::MAX),
+                )];
+                &whole_surface
+            }
+        };
+        let region = self.create_region(rects);
+        self.surface.set_opaque_region(Some(&region));
+        region.destroy();
+    }
+
+    /// Whether the compositor delivers input at `position`, i.e. it lies on the surface and inside
+    /// the input region set with [`Self::set_input_region_only`], if any.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn input_region_contains(&self, position: LogicalPosition) -> bool {
+        let x = (position.x * self.scale_factor).floor() as i32;
+        let y = (position.y * self.scale_factor).floor() as i32;
+        let contains = |&(rx, ry, width, height): &(i32, i32, i32, i32)| {
+            x >= rx && y >= ry && x < rx + width && y < ry + height
+        };
+
+        let width = i32::try_from(self.size.width).unwrap_or(i32::MAX);
+        let height = i32::try_from(self.size.height).unwrap_or(i32::MAX);
+        contains(&(0, 0, width, height))
+            && self
+                .input_region
+                .as_ref()
+                .is_none_or(|rects| rects.iter().any(contains))
+    }
+
+    fn create_region(&self, rects: &[(i32, i32, i32, i32)]) -> WlRegion {
+        let region = self.compositor.create_region(&self.queue_handle, ());
+        for &(x, y, width, height) in rects {
+            region.add(x, y, width, height);
+        }
+        region
+    }
+
+    /// Covers the surface with a fully transparent buffer of its current size, leaving it empty
+    /// but mapped, since attaching no buffer would unmap the layer surface. Rendering is
+    /// suspended until [`Self::restore`].
+    pub fn blank(&mut self) {
+        info!("Blanking layer surface");
+        self.commit_transparent_buffer();
+        self.blanked = true;
+    }
+
+    /// Resumes rendering on a blanked surface, showing the attached image again if there is one.
+    pub fn restore(&mut self) {
+        if !self.blanked {
+            return;
+        }
+        info!("Restoring blanked layer surface");
+        self.blanked = false;
+        if let Some(image) = &self.image {
+            self.surface.attach(Some(image.buffer()), 0, 0);
+            self.surface.damage(0, 0, i32::MAX, i32::MAX);
+            self.surface.commit();
+        }
+        self.render_once();
+    }
+
+    pub const fn is_blanked(&self) -> bool {
+        self.blanked
+    }
+
+    pub fn handle_configure(&mut self, width: u32, height: u32) {
+        if self.configure_diagnostics == ConfigureDiagnostics::Pending {
+            self.configure_diagnostics = ConfigureDiagnostics::Done;
+            self.diagnose_configure(width, height);
+        }
+        // The compositor's size wins; zero means it leaves that dimension to the client.
+        let width = if width > 0 {
+            width
+        } else {
+            self.surface_width()
+        };
+        let height = if height > 0 {
+            height
+        } else if self.anchor.is_all() {
+            self.surface_output_size().height
+        } else {
+            self.height()
+        };
+        self.update_size(width, height);
+        if self.mapping == SurfaceMapping::AwaitingConfigure {
+            self.mapping = SurfaceMapping::Configured;
+            self.commit_placeholder();
+        }
+        if self.blanked {
+            // Keeps covering the whole surface at its new size.
+            self.commit_transparent_buffer();
+        }
+        if self.is_static_rendering() {
+            self.render_once();
+        }
+        if let Some(auto_close) = self.auto_close.as_mut().filter(|a| !a.mapped) {
+            auto_close.mapped = true;
+            self.start_auto_close();
+        }
+    }
+
+    /// Maps the surface with a fully transparent buffer of the configured size, so nothing but
+    /// the final content is ever shown, however long the first real frame takes, e.g. with a
+    /// deferred renderer or a paused output.
+    fn commit_placeholder(&mut self) {
+        if self.image.is_none() {
+            self.commit_transparent_buffer();
+        }
+    }
+
+    /// Attaches a fully transparent buffer of the window size and commits. The buffer is dropped
+    /// once the compositor releases it.
+    fn commit_transparent_buffer(&mut self) {
+        let size = self.window.size();
+        let Ok(len) = usize::try_from(u64::from(size.width) * u64::from(size.height) * 4) else {
+            return;
+        };
+        if len == 0 {
+            return;
+        }
+        let placeholder = ShmImage::new(
+            &self.shm,
+            &self.queue_handle,
+            &vec![0; len],
+            size.width,
+            size.height,
+            wl_shm::Format::Argb8888,
+        );
+        match placeholder {
+            Ok(placeholder) => {
+                self.surface.attach(Some(placeholder.buffer()), 0, 0);
+                self.surface.damage(0, 0, i32::MAX, i32::MAX);
+                self.surface.commit();
+                self.placeholders.push(placeholder);
+            }
+            Err(e) => warn!("Failed to commit transparent buffer: {e}"),
+        }
+    }
+
+    /// Warns when the size the compositor granted in the first configure differs from the
+    /// requested one, naming the anchor combination that most likely caused it.
+    fn diagnose_configure(&self, width: u32, height: u32) {
+        if self.anchor.is_all() {
+            return;
+        }
+        let anchor = self.anchor;
+        let requested_height = self.clamp_to_output_height(self.height);
+        if height != 0 && height != requested_height {
+            if anchor.contains(Anchor::Top | Anchor::Bottom) {
+                warn!(
+                    "Requested height {requested_height} but got {height}: the surface is anchored to both top and bottom, so the compositor stretches it. Unset one of them."
+                );
+            } else {
+                warn!("Requested height {requested_height} but the compositor configured {height}");
+            }
+        }
+        if let Some(requested_width) = self.requested_width() {
+            if width != 0 && width != requested_width {
+                warn!("Requested width {requested_width} but the compositor configured {width}");
+            }
+            return;
+        }
+        let output_width = self.surface_output_size().width;
+        if width != 0 && output_width != 0 && width != output_width {
+            if anchor.contains(Anchor::Left | Anchor::Right) {
+                warn!("Expected the output width {output_width} but the compositor configured {width}");
+            } else {
+                warn!(
+                    "The compositor configured width {width} instead of the output width {output_width}: the surface is not anchored to both left and right. Anchor it to both for a full-width surface."
+                );
+            }
+        }
+    }
+
+    /// Reports a fatal protocol error to `on_protocol_error` and stops the event loop, since no
+    /// further request can succeed on the connection.
+    pub fn handle_protocol_error(&self, description: &str) {
+        error!("Wayland protocol error: {description}");
+        self.callbacks.protocol_error(description);
+        self.loop_signal.stop();
+        self.loop_signal.wakeup();
+    }
+
+    /// Handles the compositor closing the layer surface: moves it to the output picked by
+    /// `on_closed`, or closes for good if that returns `None`. Without `on_closed`, another
+    /// output accepted by the output matcher is picked if there is one.
+    pub fn handle_closed(&mut self) {
+        if matches!(
+            self.mapping,
+            SurfaceMapping::Detached | SurfaceMapping::Closed
+        ) {
+            return;
+        }
+        if self.callbacks.on_closed.is_none() {
+            if let Some(output) = self.matching_output() {
+                if let Err(e) = self.move_to_output(&output) {
+                    error!("Failed to move the surface to a matching output: {e}");
+                    self.close_after_compositor();
+                }
+                return;
+            }
+            if self.output_loss_behavior == OutputLossBehavior::WaitForReturn {
+                self.detach_from_output();
+            } else {
+                self.close_after_compositor();
+            }
+            return;
+        }
+        let available: Vec<String> = self
+            .outputs
+            .iter()
+            .filter(|info| info.output != self.output)
+            .filter_map(|info| info.name.clone())
+            .collect();
+        let Some(name) = self.callbacks.closed(&available) else {
+            self.close_after_compositor();
+            return;
+        };
+        let output = self
+            .outputs
+            .iter()
+            .find(|info| info.name.as_deref() == Some(name.as_str()))
+            .map(|info| info.output.clone());
+        let Some(output) = output else {
+            warn!("Cannot move the surface to unknown output {name}, closing");
+            self.close_after_compositor();
+            return;
+        };
+        if let Err(e) = self.move_to_output(&output) {
+            error!("Failed to move the surface to output {name}: {e}");
+            self.close_after_compositor();
+        }
+    }
+
+    /// An output other than the current one that the output matcher accepts.
+    fn matching_output(&self) -> Option<WlOutput> {
+        let matcher = self.output_matcher.as_ref()?;
+        self.outputs
+            .iter()
+            .filter(|info| info.output != self.output)
+            .find(|info| matcher(&info.info()))
+            .map(|info| info.output.clone())
+    }
+
+    /// Closes for good after the compositor closed the surface, letting the close handler run
+    /// before the loop stops.
+    fn close_after_compositor(&mut self) {
+        self.callbacks.surface_closed();
+        self.close();
+    }
+
+    /// Re-creates the surface on its output, so the compositor stacks it above the surfaces
+    /// mapped on its layer since, see [`crate::LayerShika::with_z_hint`].
+    pub fn restack(&mut self) {
+        if matches!(
+            self.mapping,
+            SurfaceMapping::Detached | SurfaceMapping::Closed
+        ) {
+            return;
+        }
+        info!("Re-mapping layer surface above a surface with a lower z_hint");
+        let output = self.output.clone();
+        if let Err(e) = self.move_to_output(&output) {
+            warn!("Failed to re-map layer surface: {e}");
+        }
+    }
+
+    /// Re-creates the surface on `output`, keeping the component and the layer state. An
+    /// attached image is dropped in favour of the component.
+    fn move_to_output(&mut self, output: &WlOutput) -> Result<(), LayerShikaError> {
+        info!("Re-creating layer surface on another output");
+        if self.mapping != SurfaceMapping::Detached {
+            self.destroy_surface()?;
+        }
+
+        let surface = Rc::new(self.compositor.create_surface(&self.queue_handle, ()));
+        self.layer_surface = Rc::new(LayerSurface::Wayland(self.layer_shell.get_layer_surface(
+            &surface,
+            Some(output),
+            self.layer,
+            self.namespace.clone(),
+            &self.queue_handle,
+            (),
+        )));
+        self.viewport = self
+            .viewporter
+            .as_ref()
+            .map(|viewporter| viewporter.get_viewport(&surface, &self.queue_handle, ()));
+        if let Some(fractional) = self.fractional_scale.as_mut() {
+            fractional.object =
+                fractional
+                    .manager
+                    .get_fractional_scale(&surface, &self.queue_handle, ());
+            fractional.preferred = None;
+        }
+        self.surface = surface;
+        self.output = output.clone();
+        self.output_activity = OutputActivity::Active;
+        self.entered_outputs.clear();
+        self.mapping = SurfaceMapping::AwaitingConfigure;
+        self.adopt_output_info();
+        // Sized by the first configure on the new output.
+        self.size = PhysicalSize::default();
+        self.scale_mismatch_reported = false;
+        self.scale_conflict_reported = None;
+        if self.buffer_scale.is_some() {
+            self.buffer_scale = Some(1);
+            self.apply_output_scale();
+        }
+        if let Some(rects) = &self.input_region {
+            let region = self.create_region(rects);
+            self.surface.set_input_region(Some(&region));
+            region.destroy();
+        }
+        self.apply_opaque_region();
+
+        self.layer_surface.set_anchor(self.anchor);
+        self.apply_margin();
+        self.layer_surface
+            .set_exclusive_zone(self.effective_exclusive_zone());
+        self.layer_surface
+            .set_keyboard_interactivity(self.keyboard_interactivity);
+        if self.anchor.is_all() {
+            self.layer_surface.set_size(0, 0);
+        } else {
+            self.layer_surface.set_size(
+                self.requested_width().unwrap_or(1),
+                self.clamp_to_output_height(self.height),
+            );
+        }
+        self.surface.commit();
+
+        let builder = self
+            .context_builder
+            .clone()
+            .with_surface_id(self.surface.id());
+        if self.pending_context.is_some() {
+            self.pending_context = Some(builder);
+        } else {
+            let context = builder.with_size(self.window.size()).build()?;
+            self.window.attach_context(context)?;
+        }
+        self.window.request_redraw();
+        Ok(())
+    }
+
+    /// Destroys the surface and everything created for it, keeping the component and the layer
+    /// state for the next one.
+    fn destroy_surface(&mut self) -> Result<(), LayerShikaError> {
+        if self.pending_context.is_none() {
+            self.window.detach_context()?;
+        }
+        self.detach_buffer();
+        if let Some(viewport) = self.viewport.take() {
+            viewport.destroy();
+        }
+        if let Some(fractional) = &self.fractional_scale {
+            fractional.object.destroy();
+        }
+        self.layer_surface.destroy();
+        self.surface.destroy();
+        // No leave event arrives for a destroyed surface.
+        self.pointer_focus = PointerFocus::Elsewhere;
+        Ok(())
+    }
+
+    /// Destroys the surface after its output went away and waits for an output with the same
+    /// name, see [`Self::handle_output_done`].
+    fn detach_from_output(&mut self) {
+        info!(
+            "Waiting for output {} to return",
+            self.output_name.as_deref().unwrap_or("<unnamed>")
+        );
+        self.pause_auto_close();
+        if let Err(e) = self.destroy_surface() {
+            error!("Failed to tear down the layer surface: {e}");
+            self.close();
+            return;
+        }
+        self.mapping = SurfaceMapping::Detached;
+    }
+
+    /// Takes over what is known about the output the surface is on, e.g. after moving to it.
+    pub fn adopt_output_info(&mut self) {
+        if let Some(info) = self.outputs.iter().find(|info| info.output == self.output) {
+            self.output_size = info.size;
+            self.output_refresh_mhz = info.refresh_mhz;
+            self.output_scale = info.scale;
+            self.output_name.clone_from(&info.name);
+        }
+    }
+
+    /// Binds an output plugged in after startup, so the surface can move to or return to it.
+    pub fn handle_output_added(&mut self, registry: &WlRegistry, global_name: u32, version: u32) {
+        info!("Output added");
+        let output =
+            registry.bind::<WlOutput, _, _>(global_name, version.min(4), &self.queue_handle, ());
+        self.track_output(global_name, output.clone());
+        self.relay(SharedEvent::OutputAdded(global_name, output));
+    }
+
+    /// Starts tracking an output bound after startup.
+    pub fn track_output(&mut self, global_name: u32, output: WlOutput) {
+        self.outputs.push(TrackedOutput::new(global_name, output));
+    }
+
+    /// Everything known about the outputs, in the order they were bound.
+    pub fn outputs(&self) -> &[TrackedOutput] {
+        &self.outputs
+    }
+
+    /// Forgets an unplugged output, closing the surface as the compositor would if it was on it.
+    pub fn handle_output_removed(&mut self, global_name: u32) {
+        let Some(index) = self
+            .outputs
+            .iter()
+            .position(|info| info.global_name == global_name)
+        else {
+            return;
+        };
+        let removed = self.outputs.remove(index);
+        info!("Output {:?} removed", removed.name);
+        self.entered_outputs
+            .retain(|entered| *entered != removed.output);
+        if removed.output == self.output {
+            self.handle_closed();
+        }
+        // A shared output is released by the surface that bound it.
+        let owned = !matches!(self.shared_globals, SharedGlobals::Borrowed);
+        if owned && removed.output.version() >= 3 {
+            removed.output.release();
+        }
+        self.relay(SharedEvent::OutputRemoved(global_name));
+        self.schedule_outputs_settled();
+    }
+
+    /// Re-creates a detached surface once `output` has advertised everything and turns out to be
+    /// one the output matcher accepts. Without a matcher it must be the one the surface was on,
+    /// or any output if that one never had a name.
+    pub fn handle_output_done(&mut self, output: &WlOutput) {
+        if self.mapping != SurfaceMapping::Detached || *output == self.output {
+            return;
+        }
+        let returned = self
+            .outputs
+            .iter()
+            .find(|info| info.output == *output)
+            .is_some_and(|info| match &self.output_matcher {
+                Some(matcher) => matcher(&info.info()),
+                None => self.output_name.is_none() || info.name == self.output_name,
+            });
+        if !returned {
+            return;
+        }
+        info!("Output returned, re-creating the layer surface");
+        if let Err(e) = self.move_to_output(output) {
+            error!("Failed to re-create the layer surface: {e}");
+            self.close();
+        }
+    }
+
+    /// Hides the surface and stops the event loop, making `run` return.
+    pub fn close(&mut self) {
+        info!("Closing layer surface");
+        self.mapping = SurfaceMapping::Closed;
+        self.pause_auto_close();
+        self.blank();
+        self.loop_signal.stop();
+        self.loop_signal.wakeup();
+    }
+
+    /// Whether [`Self::close`] ran, possibly before the event loop started and could be stopped.
+    pub fn is_closed(&self) -> bool {
+        self.mapping == SurfaceMapping::Closed
+    }
+
+    /// Whether the surface was destroyed and waits for its output to return.
+    pub fn is_detached(&self) -> bool {
+        self.mapping == SurfaceMapping::Detached
+    }
+
+    /// Whether the surface is on `output`, or about to be mapped there.
+    pub fn shows_output(&self, output: &WlOutput) -> bool {
+        self.output == *output
+            && !matches!(
+                self.mapping,
+                SurfaceMapping::Detached | SurfaceMapping::Closed
+            )
+    }
+
+    /// Queues `event` for the mirrors sharing this surface's outputs and input devices, if any.
+    pub fn relay(&mut self, event: SharedEvent) {
+        if let SharedGlobals::Relayed(events) = &mut self.shared_globals {
+            events.push(event);
+        }
+    }
+
+    /// The events queued for the mirrors since the last call.
+    pub fn take_relayed_events(&mut self) -> Vec<SharedEvent> {
+        match &mut self.shared_globals {
+            SharedGlobals::Relayed(events) => std::mem::take(events),
+            SharedGlobals::Own | SharedGlobals::Borrowed => Vec::new(),
+        }
+    }
+
+    /// (Re)starts the auto-close countdown once the surface has been mapped.
+    pub fn start_auto_close(&mut self) {
+        self.pause_auto_close();
+        let Some(auto_close) = self.auto_close.as_ref().filter(|a| a.mapped) else {
+            return;
+        };
+        let timer = self.loop_handle.insert_source(
+            Timer::from_duration(auto_close.timeout),
+            |_, (), state| {
+                info!("Auto-close timeout elapsed");
+                if let Some(auto_close) = state.auto_close.as_mut() {
+                    auto_close.timer = None;
+                }
+                state.close();
+                TimeoutAction::Drop
+            },
+        );
+        match timer {
+            Ok(token) => {
+                if let Some(auto_close) = self.auto_close.as_mut() {
+                    auto_close.timer = Some(token);
+                }
+            }
+            Err(e) => warn!("Failed to start auto-close timer: {}", e),
+        }
+    }
+
+    /// Stops the auto-close countdown, e.g. while the pointer hovers the surface.
+    pub fn pause_auto_close(&mut self) {
+        if let Some(token) = self.auto_close.as_mut().and_then(|a| a.timer.take()) {
+            self.loop_handle.remove(token);
+        }
+    }
+
+    /// In static rendering mode, frames are only drawn when explicitly requested through
+    /// [`Self::render_once`] (and after the compositor reconfigures the surface), no matter how
+    /// often the component marks itself dirty.
+    pub const fn set_static_rendering(&mut self, static_rendering: bool) {
+        self.render_mode = if static_rendering {
+            RenderMode::Static { pending: true }
+        } else {
+            RenderMode::Continuous
+        };
+    }
+
+    pub const fn is_static_rendering(&self) -> bool {
+        matches!(self.render_mode, RenderMode::Static { .. })
+    }
+
+    /// Draws and commits a single frame on the next event loop iteration.
+    pub fn render_once(&mut self) {
+        if let RenderMode::Static { pending } = &mut self.render_mode {
+            *pending = true;
+        }
+        self.window.request_redraw();
+    }
+
+    /// Reports `color_scheme` to the component's styles and redraws with it.
+    #[cfg(feature = "unstable-slint-internals")]
+    pub fn set_color_scheme(&mut self, color_scheme: ColorScheme) {
+        self.window.set_color_scheme(color_scheme);
+        self.render_once();
+    }
+
+    /// Redraws `@tr` strings with the current translations, e.g. after the application switched
+    /// gettext's locale to `language`. The environment is not written at runtime, so this does
+    /// not change the language by itself. See [`crate::LayerShika::with_language`] for the
+    /// translation backend this relies on.
+    #[cfg(feature = "unstable-slint-internals")]
+    pub fn set_language(&mut self, language: &str) {
+        language::switch_language(language);
+        self.render_once();
+    }
+
+    pub fn render_frame_if_dirty(&mut self) -> Result<(), LayerShikaError> {
+        if self.blanked
+            || self.mapping == SurfaceMapping::Detached
+            || self.image.is_some()
+            || self.is_output_paused()
+            || self.output_activity == OutputActivity::Inactive
+        {
+            return Ok(());
+        }
+        // Only requests the new height; this frame is still drawn at the current size, and the
+        // configure granting the new one triggers the next.
+        self.follow_preferred_height();
+        if let RenderMode::Static { pending } = &mut self.render_mode {
+            if !*pending {
+                return Ok(());
+            }
+            *pending = false;
+        }
+        if self.window.is_dirty() {
+            self.ensure_renderer()?;
+        }
+        if self.window.is_dirty() && self.visibility.frame_requested(Instant::now()) {
+            // Committed together with the frame by the buffer swap.
+            self.surface.frame(&self.queue_handle, ());
+        }
+        self.window.render_frame_if_dirty()
+    }
+
+    /// Creates the deferred EGL context, sized to the current buffer, and hands it to the renderer.
+    fn ensure_renderer(&mut self) -> Result<(), LayerShikaError> {
+        if let Some(builder) = self.pending_context.take() {
+            info!("Creating deferred EGL context");
+            let context = builder.with_size(self.window.size()).build()?;
+            self.window.attach_context(context)?;
+        }
+        Ok(())
+    }
+
+    /// Shows a pre-rendered image instead of the component: `data` is copied into a shared memory
+    /// buffer that is attached and committed right away, and Slint rendering is suspended until
+    /// [`Self::detach_buffer`]. The image is scaled to the surface when `wp_viewporter` is
+    /// available, otherwise it should match the surface size.
+    pub fn attach_buffer(
+        &mut self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        format: wl_shm::Format,
+    ) -> Result<(), LayerShikaError> {
+        let image = ShmImage::new(&self.shm, &self.queue_handle, data, width, height, format)?;
+        self.surface.attach(Some(image.buffer()), 0, 0);
+        self.surface.damage(0, 0, i32::MAX, i32::MAX);
+        self.surface.commit();
+        let previous = self.image.replace(image);
+        self.retire_image(previous);
+        Ok(())
+    }
+
+    /// Keeps a replaced image alive until the compositor releases its buffer, since destroying
+    /// it earlier could corrupt a frame that is still being composited.
+    fn retire_image(&mut self, image: Option<ShmImage>) {
+        if let Some(image) = image.filter(|image| !image.is_released()) {
+            self.retired_images.push(image);
+        }
+    }
+
+    pub fn handle_buffer_release(&mut self, buffer: &WlBuffer) {
+        if let Some(index) = self
+            .placeholders
+            .iter()
+            .position(|placeholder| placeholder.buffer() == buffer)
+        {
+            self.placeholders.swap_remove(index);
+            return;
+        }
+        self.retired_images.retain(|image| image.buffer() != buffer);
+        if let Some(image) = self.image.as_mut().filter(|image| image.buffer() == buffer) {
+            image.set_released();
+        }
+        self.callbacks.buffer_released();
+    }
+
+    /// Drops the attached image and goes back to rendering the component.
+    pub fn detach_buffer(&mut self) {
+        let image = self.image.take();
+        if image.is_some() {
+            self.retire_image(image);
+            self.window.request_redraw();
+            if self.is_static_rendering() {
+                self.render_once();
+            }
+        }
+    }
+
+    pub fn frame_done(&mut self) {
+        self.visibility.frame_done(Instant::now());
+    }
+
+    /// Reports a debounced visibility change to `on_visibility_changed`, if there is one.
+    pub fn check_visibility(&mut self) {
+        if let Some(visible) = self.visibility.poll(Instant::now()) {
+            info!(
+                "Surface became {}",
+                if visible { "visible" } else { "hidden" }
+            );
+            self.callbacks.visibility_changed(visible);
+            self.set_output_enabled(visible);
+        }
+    }
+
+    pub fn handle_surface_enter(&mut self, output: &WlOutput) {
+        if !self.entered_outputs.contains(output) {
+            self.entered_outputs.push(output.clone());
+            self.schedule_outputs_settled();
+        }
+        if *output == self.output {
+            self.set_output_enabled(true);
+        }
+    }
+
+    pub fn handle_surface_leave(&mut self, output: &WlOutput) {
+        let count = self.entered_outputs.len();
+        self.entered_outputs.retain(|entered| entered != output);
+        if self.entered_outputs.len() != count {
+            self.schedule_outputs_settled();
+        }
+        if *output == self.output {
+            self.set_output_enabled(false);
+        }
+    }
+
+    /// (Re)starts the countdown to `on_outputs_settled`, so a burst of output events results in
+    /// a single notification once it is over.
+    pub fn schedule_outputs_settled(&mut self) {
+        if self.callbacks.on_outputs_settled.is_none() {
+            return;
+        }
+        if let Some(token) = self.outputs_settle_timer.take() {
+            self.loop_handle.remove(token);
+        }
+        let timer = self.loop_handle.insert_source(
+            Timer::from_duration(OUTPUTS_SETTLE_DELAY),
+            |_, (), state| {
+                state.outputs_settle_timer = None;
+                let names: Vec<String> = state
+                    .outputs
+                    .iter()
+                    .filter_map(|info| info.name.clone())
+                    .collect();
+                info!("Outputs settled: {names:?}");
+                state.callbacks.outputs_settled(&names);
+                TimeoutAction::Drop
+            },
+        );
+        match timer {
+            Ok(token) => self.outputs_settle_timer = Some(token),
+            Err(e) => warn!("Failed to start output settle timer: {}", e),
+        }
+    }
+
+    /// Names of the outputs currently showing the surface, as reported by `wl_surface.enter`
+    /// and `leave`. Usually just the one it was created on, but a surface can span several.
+    /// Outputs that haven't advertised a name yet are left out.
+    pub fn current_outputs(&self) -> Vec<String> {
+        self.entered_outputs
+            .iter()
+            .filter_map(|output| self.outputs.iter().find(|info| info.output == *output))
+            .filter_map(|info| info.name.clone())
+            .collect()
+    }
+
+    /// Reports the output becoming active or inactive to `on_output_enabled` and suspends
+    /// rendering while it is inactive. The frame callback still outstanding from the last frame
+    /// fires once the output presents again, which is how it is noticed coming back.
+    fn set_output_enabled(&mut self, enabled: bool) {
+        if self.callbacks.on_output_enabled.is_none() {
+            return;
+        }
+        let activity = if enabled {
+            OutputActivity::Active
+        } else {
+            OutputActivity::Inactive
+        };
+        if self.output_activity == activity {
+            return;
+        }
+        info!(
+            "Output became {}",
+            if enabled { "active" } else { "inactive" }
+        );
+        self.output_activity = activity;
+        self.callbacks.output_enabled(enabled);
+        if enabled {
+            self.window.request_redraw();
+            if self.is_static_rendering() {
+                self.render_once();
+            }
+        }
+    }
+
+    /// Whether the compositor is currently presenting the surface, as far as frame callbacks tell.
+    pub const fn is_visible(&self) -> bool {
+        self.visibility.is_visible()
+    }
+
+    /// Whether frame callbacks need to be polled, for `on_visibility_changed` or
+    /// `on_output_enabled`.
+    pub const fn tracks_visibility(&self) -> bool {
+        self.callbacks.on_visibility_changed.is_some() || self.callbacks.on_output_enabled.is_some()
+    }
+
+    pub const fn keyboard_state_mut(&mut self) -> &mut KeyboardState {
+        &mut self.keyboard_state
+    }
+
+    pub const fn keyboard(&self) -> Option<&WlKeyboard> {
+        self.seat_devices.keyboard()
+    }
+
+    pub fn handle_key(&mut self, key: u32, pressed: bool) {
+        if pressed {
+            self.stop_key_repeat();
+            self.start_key_repeat(key);
+        } else if self.key_repeat.is_some_and(|(held, _)| held == key) {
+            self.stop_key_repeat();
+        }
+        self.emit_key(key, pressed);
+    }
+
+    /// Re-sends `key` as pressed after the compositor's repeat delay, then at its repeat rate,
+    /// until it is released, another key is pressed or focus is lost.
+    fn start_key_repeat(&mut self, key: u32) {
+        let Some((delay, interval)) = self.keyboard_state.repeat_timing(key) else {
+            return;
+        };
+        let timer =
+            self.loop_handle
+                .insert_source(Timer::from_duration(delay), move |_, (), state| {
+                    state.emit_key(key, true);
+                    TimeoutAction::ToDuration(interval)
+                });
+        match timer {
+            Ok(token) => self.key_repeat = Some((key, token)),
+            Err(e) => warn!("Failed to start key repeat timer: {}", e),
+        }
+    }
+
+    fn stop_key_repeat(&mut self) {
+        if let Some((_, token)) = self.key_repeat.take() {
+            self.loop_handle.remove(token);
+        }
+    }
+
+    /// Reports the key to `on_key` and delivers it to the component, e.g. to a focused
+    /// `TextInput`.
+    fn emit_key(&self, key: u32, pressed: bool) {
+        if let Some(keysym) = self.keyboard_state.keysym(key) {
+            self.callbacks
+                .key(keysym, self.keyboard_state.modifiers(), pressed);
+        }
+        if let Some(text) = self.keyboard_state.key_text(key) {
+            let event = if pressed {
+                WindowEvent::KeyPressed { text }
+            } else {
+                WindowEvent::KeyReleased { text }
+            };
+            self.window.dispatch_event(event);
+        }
+    }
+
+    pub fn handle_keyboard_enter(&mut self) {
+        info!("Keyboard focus gained");
+        self.keyboard_state.set_focused(true);
+        self.window
+            .dispatch_event(WindowEvent::WindowActiveChanged(true));
+        self.callbacks.keyboard_focus_changed(true);
+    }
+
+    pub fn handle_keyboard_leave(&mut self) {
+        info!("Keyboard focus lost");
+        self.keyboard_state.set_focused(false);
+        self.stop_key_repeat();
+        self.window
+            .dispatch_event(WindowEvent::WindowActiveChanged(false));
+        self.callbacks.keyboard_focus_changed(false);
+        self.callbacks.focus_lost();
+    }
+
+    /// Whether the surface currently has keyboard focus, as opposed to merely requesting it.
+    pub const fn has_keyboard_focus(&self) -> bool {
+        self.keyboard_state.has_focus()
+    }
+
+    /// Whether input events naming `surface` are meant for this state.
+    pub fn is_own_surface(&self, surface: &WlSurface) -> bool {
+        *self.surface == *surface
+    }
+
+    pub const fn set_pointer_focus(&mut self, focused: bool) {
+        self.pointer_focus = if focused {
+            PointerFocus::Surface
+        } else {
+            PointerFocus::Elsewhere
+        };
+    }
+
+    pub fn has_pointer_focus(&self) -> bool {
+        self.pointer_focus == PointerFocus::Surface
     }
 
     #[allow(clippy::cast_possible_truncation)]
@@ -79,6 +1962,111 @@ impl WindowState {
         self.current_pointer_position = logical_position;
     }
 
+    /// Holds a pointer event back until the `wl_pointer.frame` that ends the group it belongs to.
+    pub fn queue_pointer_event(&mut self, event: WindowEvent) {
+        self.pointer_frame.events.push(event);
+    }
+
+    /// Adds a `wl_pointer.axis` distance, in surface coordinates, to the scroll of the current
+    /// frame.
+    pub fn handle_pointer_axis(&mut self, axis: wl_pointer::Axis, distance: f64) {
+        if let Some(scroll) = self.axis_scroll_mut(axis) {
+            scroll.distance += distance;
+        }
+    }
+
+    /// Adds wheel rotation in 1/120 of a detent, from `wl_pointer.axis_value120` or
+    /// `axis_discrete`, to the scroll of the current frame, so high-resolution wheels sending
+    /// fractions of a detent scroll by the sum.
+    pub fn handle_pointer_axis_value120(&mut self, axis: wl_pointer::Axis, value120: i32) {
+        if let Some(scroll) = self.axis_scroll_mut(axis) {
+            scroll.value120 = Some(scroll.value120.unwrap_or(0).saturating_add(value120));
+        }
+    }
+
+    fn axis_scroll_mut(&mut self, axis: wl_pointer::Axis) -> Option<&mut AxisScroll> {
+        match axis {
+            wl_pointer::Axis::HorizontalScroll => Some(&mut self.pointer_frame.horizontal),
+            wl_pointer::Axis::VerticalScroll => Some(&mut self.pointer_frame.vertical),
+            _ => None,
+        }
+    }
+
+    /// Delivers the pointer events of the frame that just ended, then its accumulated scroll.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn flush_pointer_frame(&mut self) {
+        let PointerFrame {
+            events,
+            horizontal,
+            vertical,
+        } = std::mem::take(&mut self.pointer_frame);
+        for event in events {
+            self.dispatch_input(InputSource::Pointer, event);
+        }
+        let (horizontal, vertical) = (horizontal.distance(), vertical.distance());
+        if horizontal != 0.0 || vertical != 0.0 {
+            let scale_factor = self.scale_factor;
+            // Wayland counts positive values downwards and to the right, which Slint treats as
+            // scrolling back.
+            let event = WindowEvent::PointerScrolled {
+                position: self.current_pointer_position,
+                delta_x: -horizontal as f32 / scale_factor,
+                delta_y: -vertical as f32 / scale_factor,
+            };
+            self.dispatch_input(InputSource::Pointer, event);
+        }
+    }
+
+    pub const fn set_last_pointer_serial(&mut self, serial: u32) {
+        self.last_pointer_serial = Some(serial);
+    }
+
+    /// Delivers a pointer or touch event to the component unless the input filter consumes it.
+    pub fn dispatch_input(&mut self, source: InputSource, event: WindowEvent) {
+        let event = InputEvent { source, event };
+        if self.callbacks.filter_input(&event) == FilterAction::Forward {
+            self.window.dispatch_event(event.event);
+        }
+    }
+
+    /// Creates and releases input devices as the seat gains and loses capabilities.
+    pub fn handle_seat_capabilities(&mut self, capabilities: WEnum<wl_seat::Capability>) {
+        let removed = self.seat_devices.update(capabilities, &self.queue_handle);
+        self.release_input_focus(removed);
+        if !removed.is_empty() {
+            self.relay(SharedEvent::DevicesRemoved(removed));
+        }
+    }
+
+    /// Ends pointer, keyboard and touch interaction through the devices in `removed`.
+    pub fn release_input_focus(&mut self, removed: wl_seat::Capability) {
+        if removed.contains(wl_seat::Capability::Pointer) && self.has_pointer_focus() {
+            self.pointer_frame = PointerFrame::default();
+            self.pointer_focus = PointerFocus::Elsewhere;
+            self.dispatch_input(InputSource::Pointer, WindowEvent::PointerExited);
+        }
+        if removed.contains(wl_seat::Capability::Keyboard) && self.has_keyboard_focus() {
+            self.handle_keyboard_leave();
+        }
+        if removed.contains(wl_seat::Capability::Touch) && self.active_touch.take().is_some() {
+            self.dispatch_input(InputSource::Touch, WindowEvent::PointerExited);
+        }
+    }
+
+    pub const fn active_touch(&self) -> Option<i32> {
+        self.active_touch
+    }
+
+    pub const fn set_active_touch(&mut self, id: Option<i32>) {
+        self.active_touch = id;
+    }
+
+    /// Serial of the most recent pointer button event, as required by serial-taking requests
+    /// such as popup grabs. `None` until the first button press or release on the surface.
+    pub const fn last_pointer_serial(&self) -> Option<u32> {
+        self.last_pointer_serial
+    }
+
     pub const fn size(&self) -> &PhysicalSize {
         &self.size
     }
@@ -91,7 +2079,7 @@ impl WindowState {
         Rc::clone(&self.window)
     }
 
-    pub fn layer_surface(&self) -> Rc<ZwlrLayerSurfaceV1> {
+    pub fn layer_surface(&self) -> Rc<LayerSurface> {
         Rc::clone(&self.layer_surface)
     }
 
@@ -103,7 +2091,45 @@ impl WindowState {
         self.height
     }
 
-    pub fn set_output_size(&mut self, output_size: PhysicalSize) {
+    pub const fn anchor(&self) -> Anchor {
+        self.anchor
+    }
+
+    pub const fn margin(&self) -> (i32, i32, i32, i32) {
+        self.margin
+    }
+
+    pub const fn keyboard_interactivity(&self) -> KeyboardInteractivity {
+        self.keyboard_interactivity
+    }
+
+    pub const fn exclusive_zone(&self) -> i32 {
+        self.exclusive_zone
+    }
+
+    pub const fn layer(&self) -> Layer {
+        self.layer
+    }
+
+    pub fn config_snapshot(&self) -> ConfigSnapshot {
+        ConfigSnapshot {
+            layer: self.layer,
+            anchor: self.anchor,
+            margin: self.margin,
+            height: self.height,
+            exclusive_zone: self.exclusive_zone,
+            keyboard_interactivity: self.keyboard_interactivity,
+            scale_factor: self.scale_factor,
+            namespace: self.namespace.clone(),
+        }
+    }
+
+    /// The namespace the layer surface was created with, including any suffix.
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub const fn set_output_size(&mut self, output_size: PhysicalSize) {
         self.output_size = output_size;
     }
 
@@ -111,7 +2137,170 @@ impl WindowState {
         &self.output_size
     }
 
+    pub const fn set_output_refresh_mhz(&mut self, refresh_mhz: Option<u32>) {
+        self.output_refresh_mhz = refresh_mhz;
+    }
+
+    pub const fn set_output_scale(&mut self, scale: i32) {
+        self.output_scale = scale;
+    }
+
+    /// Whether `output` is the one the surface is currently on.
+    pub fn is_current_output(&self, output: &WlOutput) -> bool {
+        &self.output == output
+    }
+
+    /// What is known about `output`, updated from its events whether or not the surface is on it.
+    pub fn output_info_mut(&mut self, output: &WlOutput) -> Option<&mut TrackedOutput> {
+        self.outputs.iter_mut().find(|info| &info.output == output)
+    }
+
+    pub fn set_output_name(&mut self, name: String) {
+        self.output_name = Some(name);
+    }
+
+    /// Connector name of the output the surface was created on, e.g. `DP-1`, once advertised.
+    pub fn output_name(&self) -> Option<&str> {
+        self.output_name.as_deref()
+    }
+
+    /// Suspends rendering while the surface is on the output called `name`, e.g. because that
+    /// monitor is switched off. The surface stays mapped and keeps handling input; panels
+    /// replicated across outputs can call this on every instance and only the matching one stops.
+    pub fn pause_output(&mut self, name: &str) {
+        if !self.paused_outputs.iter().any(|paused| paused == name) {
+            info!("Pausing rendering on output {name}");
+            self.paused_outputs.push(name.to_owned());
+        }
+    }
+
+    /// Resumes rendering on the output called `name` and redraws.
+    pub fn resume_output(&mut self, name: &str) {
+        let count = self.paused_outputs.len();
+        self.paused_outputs.retain(|paused| paused != name);
+        if self.paused_outputs.len() != count {
+            info!("Resuming rendering on output {name}");
+            self.window.request_redraw();
+            if self.is_static_rendering() {
+                self.render_once();
+            }
+        }
+    }
+
+    pub fn is_output_paused(&self) -> bool {
+        self.output_name
+            .as_ref()
+            .is_some_and(|name| self.paused_outputs.contains(name))
+    }
+
+    /// Integer scale advertised by the output the surface was created on.
+    pub const fn output_scale(&self) -> i32 {
+        self.output_scale
+    }
+
+    /// Warns once when the configured scale factor differs from the one the output advertises,
+    /// which otherwise silently produces mis-sized rendering. Only checked when not following the
+    /// output scale: the followed scale then already matches the output, the scale factor is
+    /// only a zoom on top, and density differences are reported by `check_scale_conflict`.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn check_scale_mismatch(&mut self) {
+        let output_scale = self.output_scale as f32;
+        if self.buffer_scale.is_some() {
+            return;
+        }
+        if self.scale_mismatch_reported || (self.scale_factor - output_scale).abs() < 0.01 {
+            return;
+        }
+        self.scale_mismatch_reported = true;
+        warn!(
+            "Configured scale factor {} differs from the output scale {}; set with_scale_factor accordingly or follow the output scale",
+            self.scale_factor, output_scale
+        );
+        self.callbacks
+            .scale_mismatch(self.scale_factor, output_scale);
+    }
+
+    /// Warns when the buffer is rendered at a different density than the output displays it at,
+    /// once per conflicting output scale.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn check_scale_conflict(&mut self) {
+        if self
+            .fractional_scale
+            .as_ref()
+            .is_some_and(|fractional| fractional.preferred.is_some())
+        {
+            // The compositor picked the density itself, which the integer scale can't express.
+            return;
+        }
+        let output_scale = self.output_scale;
+        if (self.buffer_density() - output_scale as f32).abs() < 0.01 {
+            self.scale_conflict_reported = None;
+            return;
+        }
+        if self.scale_conflict_reported == Some(output_scale) {
+            return;
+        }
+        self.scale_conflict_reported = Some(output_scale);
+        warn!(
+            "Rendering at scale {} on an output with scale {}; the compositor will resample the buffer",
+            self.buffer_density(), output_scale
+        );
+        self.callbacks
+            .scale_conflict(self.buffer_density(), output_scale);
+    }
+
+    /// Refresh rate of the output's current mode in millihertz, if the compositor advertised one.
+    pub const fn output_refresh_mhz(&self) -> Option<u32> {
+        self.output_refresh_mhz
+    }
+
     pub const fn component_instance(&self) -> &ComponentInstance {
         &self.component_instance
     }
+
+    /// The definition the component was created from, updated when it is replaced.
+    pub const fn component_definition(&self) -> &ComponentDefinition {
+        &self.component_definition
+    }
+
+    /// Sets a public property of the component and schedules a redraw showing it.
+    pub fn set_property(&self, name: &str, value: Value) -> Result<(), LayerShikaError> {
+        self.component_instance
+            .set_property(name, value)
+            .map_err(|e| {
+                LayerShikaError::InvalidInput(format!("Failed to set property `{name}`: {e}"))
+            })?;
+        self.window.request_redraw();
+        Ok(())
+    }
+
+    pub fn get_property(&self, name: &str) -> Result<Value, LayerShikaError> {
+        self.component_instance.get_property(name).map_err(|e| {
+            LayerShikaError::InvalidInput(format!("Failed to get property `{name}`: {e}"))
+        })
+    }
+}
+
+impl Drop for WindowState {
+    /// Tears the surface down in protocol order, so it disappears even when an embedder keeps the
+    /// connection open after the windowing system is gone.
+    fn drop(&mut self) {
+        if self.mapping == SurfaceMapping::Detached {
+            // `destroy_surface` already tore everything down.
+            return;
+        }
+        if self.pending_context.is_none() {
+            if let Err(e) = self.window.detach_context() {
+                debug!("Failed to release the EGL context: {e}");
+            }
+        }
+        if let Some(fractional) = &self.fractional_scale {
+            fractional.object.destroy();
+        }
+        if let Some(viewport) = self.viewport.take() {
+            viewport.destroy();
+        }
+        self.layer_surface.destroy();
+        self.surface.destroy();
+    }
 }
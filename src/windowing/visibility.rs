@@ -0,0 +1,67 @@
+use std::time::{Duration, Instant};
+
+/// Tracks whether the compositor is presenting the surface, based on whether frame callbacks
+/// come back, and debounces transitions so brief occlusions don't flip the reported state.
+///
+/// Visibility can only be observed while frames are being drawn: a surface whose content is
+/// idle keeps its last reported state until it renders again.
+pub struct VisibilityTracker {
+    debounce: Duration,
+    frame_requested_at: Option<Instant>,
+    presenting_since: Option<Instant>,
+    visible: bool,
+}
+
+impl VisibilityTracker {
+    pub const fn new(debounce: Duration) -> Self {
+        Self {
+            debounce,
+            frame_requested_at: None,
+            presenting_since: None,
+            visible: true,
+        }
+    }
+
+    /// Returns whether a new frame callback should be requested, i.e. none is outstanding.
+    pub const fn frame_requested(&mut self, now: Instant) -> bool {
+        if self.frame_requested_at.is_some() {
+            return false;
+        }
+        self.frame_requested_at = Some(now);
+        true
+    }
+
+    pub fn frame_done(&mut self, now: Instant) {
+        self.frame_requested_at = None;
+        self.presenting_since.get_or_insert(now);
+    }
+
+    /// Returns the new visibility if it changed and the change has been sustained for longer
+    /// than the debounce period.
+    pub fn poll(&mut self, now: Instant) -> Option<bool> {
+        let stalled = self
+            .frame_requested_at
+            .is_some_and(|requested| now.duration_since(requested) >= self.debounce);
+        if stalled {
+            self.presenting_since = None;
+            if self.visible {
+                self.visible = false;
+                return Some(false);
+            }
+            return None;
+        }
+
+        let resumed = self
+            .presenting_since
+            .is_some_and(|since| now.duration_since(since) >= self.debounce);
+        if resumed && !self.visible {
+            self.visible = true;
+            return Some(true);
+        }
+        None
+    }
+
+    pub const fn is_visible(&self) -> bool {
+        self.visible
+    }
+}
@@ -0,0 +1,311 @@
+#![cfg(feature = "headless")]
+
+use layer_shika::{
+    sctk::protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::{
+        Anchor, KeyboardInteractivity,
+    },
+    slint::platform::software_renderer::PremultipliedRgbaColor,
+    slint::{LogicalSize, PhysicalSize},
+    slint_interpreter::Value,
+    HeadlessWindowingSystem, LayerShika, LayerSurfaceRequest, OptionalProtocols,
+};
+use std::{cell::Cell, rc::Rc};
+
+const BAR: &str = r"
+export component Bar inherits Window {
+    in-out property <int> clicks;
+    background: #ff0000;
+    TouchArea {
+        clicked => { root.clicks += 1; }
+    }
+}
+";
+
+const GROWING: &str = r"
+export component Growing inherits Window {
+    in property <length> content-height: 30px;
+    VerticalLayout {
+        Rectangle {
+            preferred-height: root.content-height;
+        }
+    }
+}
+";
+
+fn bar(builder: LayerShika) -> HeadlessWindowingSystem {
+    builder
+        .with_component_source(BAR, None)
+        .headless()
+        .expect("headless surface")
+}
+
+#[test]
+fn configure_sizes_surface_and_frame() {
+    let mut system = bar(LayerShika::new().with_height(30));
+    system.take_requests();
+
+    system.configure(0, 30);
+    assert_eq!(system.size(), PhysicalSize::new(1920, 30));
+    assert!(system
+        .take_requests()
+        .contains(&LayerSurfaceRequest::SetSize(1920, 30)));
+
+    let frame = system.render().expect("frame");
+    assert_eq!((frame.width(), frame.height()), (1920, 30));
+    let PremultipliedRgbaColor {
+        red,
+        green,
+        blue,
+        alpha,
+    } = frame.as_slice()[0];
+    assert_eq!((red, green, blue, alpha), (255, 0, 0, 255));
+}
+
+#[test]
+fn pointer_input_reaches_component() {
+    let mut system = bar(LayerShika::new().with_height(30));
+    system.configure(0, 30);
+    system.render().expect("frame");
+
+    system.pointer_enter(10., 10.);
+    system.pointer_button(true);
+    system.pointer_button(false);
+    system.pointer_leave();
+
+    assert_eq!(system.get_property("clicks").ok(), Some(Value::Number(1.)));
+}
+
+#[test]
+fn configure_keeps_granted_width_below_output_width() {
+    let mut system = bar(LayerShika::new().with_height(30));
+
+    system.configure(800, 30);
+
+    assert_eq!(system.size(), PhysicalSize::new(800, 30));
+}
+
+#[test]
+fn configure_without_width_falls_back_to_configured_width() {
+    let mut system = bar(LayerShika::new()
+        .with_anchor(Anchor::Top)
+        .with_width(600)
+        .with_height(30));
+
+    system.configure(0, 30);
+
+    assert_eq!(system.size(), PhysicalSize::new(600, 30));
+}
+
+#[test]
+fn configure_without_width_falls_back_to_output_width() {
+    let mut system = bar(LayerShika::new().with_height(30));
+
+    system.configure(0, 30);
+
+    assert_eq!(system.size(), PhysicalSize::new(1920, 30));
+}
+
+#[test]
+fn width_limited_surface_keeps_its_width_on_configure() {
+    let mut system = bar(LayerShika::new()
+        .with_anchor(Anchor::Top)
+        .with_width(600)
+        .with_height(30));
+    system.take_requests();
+
+    system.configure(600, 30);
+
+    assert_eq!(system.size(), PhysicalSize::new(600, 30));
+    let sizes: Vec<_> = system
+        .take_requests()
+        .into_iter()
+        .filter(|request| matches!(request, LayerSurfaceRequest::SetSize(..)))
+        .collect();
+    assert_eq!(sizes, [LayerSurfaceRequest::SetSize(600, 30)]);
+}
+
+#[test]
+fn anchoring_left_and_right_stretches_a_width_limited_surface() {
+    let mut system = bar(LayerShika::new()
+        .with_anchor(Anchor::Top | Anchor::Left | Anchor::Right)
+        .with_width(600)
+        .with_height(30));
+    system.take_requests();
+
+    system.configure(0, 30);
+
+    assert_eq!(system.size(), PhysicalSize::new(1920, 30));
+    assert!(system
+        .take_requests()
+        .contains(&LayerSurfaceRequest::SetSize(1920, 30)));
+}
+
+#[test]
+fn output_scale_doubles_buffer_size() {
+    let mut system = bar(LayerShika::new().with_height(30));
+    system.configure(0, 30);
+    assert_eq!(system.buffer_size(), PhysicalSize::new(1920, 30));
+
+    system.output_scale(2);
+    system.output_done();
+
+    assert_eq!(system.size(), PhysicalSize::new(1920, 30));
+    assert_eq!(system.buffer_size(), PhysicalSize::new(3840, 60));
+}
+
+#[test]
+fn resized_callback_reports_buffer_pixels() {
+    let resized = Rc::new(Cell::new(None));
+    let reported = Rc::clone(&resized);
+    let mut system = bar(LayerShika::new()
+        .with_height(30)
+        .on_resized(move |physical, logical| reported.set(Some((physical, logical)))));
+    system.configure(0, 30);
+
+    system.output_scale(2);
+    system.output_done();
+
+    assert_eq!(
+        resized.get(),
+        Some((PhysicalSize::new(3840, 60), LogicalSize::new(1920., 30.)))
+    );
+}
+
+#[test]
+fn resized_callback_skips_unchanged_sizes() {
+    let calls = Rc::new(Cell::new(0));
+    let counted = Rc::clone(&calls);
+    let mut system = bar(LayerShika::new()
+        .with_height(30)
+        .on_resized(move |_, _| counted.set(counted.get() + 1)));
+    system.configure(0, 30);
+    let after_first = calls.get();
+
+    system.configure(0, 30);
+
+    assert_eq!(after_first, 1);
+    assert_eq!(calls.get(), 1);
+}
+
+#[test]
+fn integer_output_scale_change_resizes_renderer() {
+    let mut system = bar(LayerShika::new().with_height(30));
+    system.configure(0, 30);
+    let frame = system.render().expect("frame");
+    assert_eq!((frame.width(), frame.height()), (1920, 30));
+
+    system.output_scale(2);
+    system.output_done();
+
+    let frame = system.render().expect("frame");
+    assert_eq!((frame.width(), frame.height()), (3840, 60));
+}
+
+#[test]
+fn preferred_scale_change_resizes_renderer() {
+    let mut system = LayerShika::new()
+        .with_height(30)
+        .with_component_source(BAR, None)
+        .headless_with_protocols(OptionalProtocols {
+            fractional_scale: true,
+            cursor_shape: false,
+            viewporter: true,
+        })
+        .expect("headless surface");
+    system.configure(0, 30);
+    let frame = system.render().expect("frame");
+    assert_eq!((frame.width(), frame.height()), (1920, 30));
+
+    // 180/120, i.e. a scale of 1.5.
+    system.preferred_scale(180);
+
+    let frame = system.render().expect("frame");
+    assert_eq!((frame.width(), frame.height()), (2880, 45));
+}
+
+#[test]
+fn keyboard_interactivity_switches_off_and_survives_reconfiguration() {
+    let mut system = bar(LayerShika::new()
+        .with_height(30)
+        .with_keyboard_interactivity(KeyboardInteractivity::Exclusive));
+    system.configure(0, 30);
+    system.take_requests();
+
+    system.set_keyboard_interactivity(KeyboardInteractivity::None);
+    assert!(system
+        .take_requests()
+        .contains(&LayerSurfaceRequest::SetKeyboardInteractivity(
+            KeyboardInteractivity::None
+        )));
+
+    system.configure(0, 30);
+    system.reapply_config();
+    let modes: Vec<_> = system
+        .take_requests()
+        .into_iter()
+        .filter(|request| matches!(request, LayerSurfaceRequest::SetKeyboardInteractivity(_)))
+        .collect();
+    assert_eq!(
+        modes,
+        [LayerSurfaceRequest::SetKeyboardInteractivity(
+            KeyboardInteractivity::None
+        )]
+    );
+}
+
+#[test]
+fn reapply_config_requests_configured_size() {
+    let mut system = bar(LayerShika::new()
+        .with_anchor(Anchor::Top)
+        .with_width(600)
+        .with_height(30));
+    // The compositor granted less than requested.
+    system.configure(500, 20);
+    system.take_requests();
+
+    system.reapply_config();
+
+    assert!(system
+        .take_requests()
+        .contains(&LayerSurfaceRequest::SetSize(600, 30)));
+}
+
+#[test]
+fn blanked_surface_stays_blank_across_configure_until_restored() {
+    let mut system = bar(LayerShika::new().with_height(30));
+    system.configure(0, 30);
+    system.render().expect("frame");
+
+    system.blank();
+    system.configure(0, 40);
+    let frame = system.render().expect("frame");
+    assert_eq!((frame.width(), frame.height()), (1920, 30));
+
+    system.restore();
+    let frame = system.render().expect("frame");
+    assert_eq!((frame.width(), frame.height()), (1920, 40));
+}
+
+#[test]
+fn auto_height_requests_preferred_height_and_exclusive_zone() {
+    let mut system = LayerShika::new()
+        .with_auto_height()
+        .with_exclusive_zone(30)
+        .with_component_source(GROWING, None)
+        .headless()
+        .expect("headless surface");
+    system.configure(0, 30);
+    system.render().expect("frame");
+    system.take_requests();
+
+    system
+        .set_property("content-height", Value::Number(50.))
+        .expect("property");
+    system.render().expect("frame");
+
+    let requests = system.take_requests();
+    assert!(requests
+        .iter()
+        .any(|request| matches!(request, LayerSurfaceRequest::SetSize(_, 50))));
+    assert!(requests.contains(&LayerSurfaceRequest::SetExclusiveZone(50)));
+}